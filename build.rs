@@ -28,7 +28,7 @@ fn main() {
         .opaque_type("std::(vector|string)")
         .allowlist_item("RED4ext::[^:]+")
         .allowlist_item("RED4ext::(Detail|ent)::.+")
-        .allowlist_item("RED4ext::Memory::(Vault|IAllocator)")
+        .allowlist_item("RED4ext::Memory::(Vault|IAllocator|EngineAllocator)")
         .allowlist_item("versioning::.+")
         // callback handlers generate incorrect Rust code
         .blocklist_item("RED4ext::(Detail::)?CallbackHandler.*")
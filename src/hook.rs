@@ -0,0 +1,476 @@
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use sealed::sealed;
+use thiserror::Error;
+
+use crate::types::{Function, IScriptable, StackFrame};
+use crate::{SdkEnv, VoidPtr};
+
+// every detour type gets its own slot, the trampoline is monomorphized for the detour type, so
+// it can find its slot without any additional context being passed by the caller
+static HOOKS: RwLock<BTreeMap<TypeId, Arc<HookSlot>>> = RwLock::new(BTreeMap::new());
+// the original functions are kept after a hook is detached, so that calls which were already on
+// their way into a trampoline when it was detached can still be forwarded to the original code
+static ORIGINALS: RwLock<BTreeMap<TypeId, usize>> = RwLock::new(BTreeMap::new());
+
+// the context passed to native handlers of global functions is null
+type RawNativeHandler = extern "C" fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, i64);
+
+struct HookSlot {
+    kind: HookKind,
+    target: usize,
+    original: AtomicUsize,
+    detour: Box<dyn Any + Send + Sync>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum HookKind {
+    // a detour attached through the RED4ext hooking API, the target is the function address
+    Detour,
+    // a replaced native handler of an RTTI function, the target is the address of the handler slot
+    Native,
+}
+
+/// An error returned when attaching a hook fails.
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("the target function is already hooked")]
+    AlreadyHooked,
+    #[error("the detour is already attached to another target")]
+    DetourInUse,
+    #[error("the hook could not be attached")]
+    AttachFailed,
+    #[error("the address hash {0:#x} could not be resolved")]
+    UnresolvedHash(u32),
+    #[error("the function could not be found")]
+    FunctionNotFound,
+    #[error("the function is not native")]
+    NotNative,
+    #[error("functions with out parameters cannot be observed")]
+    OutParams,
+}
+
+/// A handle to an attached hook. The hook is detached when the guard is dropped.
+#[derive(Debug)]
+#[must_use = "the hook is detached when the guard is dropped"]
+pub struct HookGuard<'a> {
+    env: &'a SdkEnv,
+    key: TypeId,
+}
+
+impl HookGuard<'_> {
+    /// Detaches the hook explicitly. Returns `true` if the hook was detached successfully.
+    #[inline]
+    pub fn detach(self) -> bool {
+        let this = mem::ManuallyDrop::new(self);
+        detach(this.env, this.key)
+    }
+}
+
+impl Drop for HookGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        detach(self.env, self.key);
+    }
+}
+
+pub(crate) fn attach<F, A, R, D>(
+    env: &SdkEnv,
+    target: F,
+    detour: D,
+) -> Result<HookGuard<'_>, HookError>
+where
+    F: FnPtr<A, R>,
+    D: Detour<F, A, R>,
+{
+    let target = target.to_ptr();
+    register::<D>(HookKind::Detour, target as usize, detour, |slot| unsafe {
+        ((*env.sdk.hooking).Attach.unwrap())(
+            env.handle,
+            target,
+            D::trampoline().to_ptr(),
+            slot.original.as_ptr().cast::<VoidPtr>(),
+        )
+    })?;
+    Ok(HookGuard {
+        env,
+        key: TypeId::of::<D>(),
+    })
+}
+
+pub(crate) fn attach_native<'a, D>(
+    env: &'a SdkEnv,
+    func: &Function,
+    detour: D,
+) -> Result<HookGuard<'a>, HookError>
+where
+    D: Fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, NativeHandler) + Send + Sync + 'static,
+{
+    let handler = func.native_handler_ptr().ok_or(HookError::NotNative)?;
+    register_native(handler.cast::<usize>(), detour)?;
+    Ok(HookGuard {
+        env,
+        key: TypeId::of::<D>(),
+    })
+}
+
+fn register_native<D>(handler: *mut usize, detour: D) -> Result<(), HookError>
+where
+    D: Fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, NativeHandler) + Send + Sync + 'static,
+{
+    let trampoline = native_trampoline::<D> as RawNativeHandler;
+    register::<D>(HookKind::Native, handler as usize, detour, |slot| {
+        let handler = unsafe { AtomicUsize::from_ptr(handler) };
+        let original = handler.swap(trampoline as usize, Ordering::AcqRel);
+        slot.original.store(original, Ordering::Release);
+        true
+    })
+}
+
+fn register<D: Send + Sync + 'static>(
+    kind: HookKind,
+    target: usize,
+    detour: D,
+    install: impl FnOnce(&HookSlot) -> bool,
+) -> Result<(), HookError> {
+    let key = TypeId::of::<D>();
+
+    let mut hooks = HOOKS.write().unwrap_or_else(PoisonError::into_inner);
+    if hooks.contains_key(&key) {
+        return Err(HookError::DetourInUse);
+    }
+    if hooks.values().any(|slot| slot.target == target) {
+        return Err(HookError::AlreadyHooked);
+    }
+
+    let slot = Arc::new(HookSlot {
+        kind,
+        target,
+        original: AtomicUsize::new(0),
+        detour: Box::new(detour),
+    });
+    // the write lock is held until the slot is inserted, so trampolines invoked in the meantime
+    // will wait for it
+    if !install(&slot) {
+        return Err(HookError::AttachFailed);
+    }
+    ORIGINALS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(key, slot.original.load(Ordering::Acquire));
+    hooks.insert(key, slot);
+    Ok(())
+}
+
+/// Detaches all hooks attached by the plugin.
+pub(crate) fn detach_all(env: &SdkEnv) {
+    let mut hooks = HOOKS.write().unwrap_or_else(PoisonError::into_inner);
+    for slot in hooks.values() {
+        uninstall(env, slot);
+    }
+    hooks.clear();
+}
+
+#[inline]
+fn detach(env: &SdkEnv, key: TypeId) -> bool {
+    detach_with(key, |slot| uninstall(env, slot))
+}
+
+fn detach_with(key: TypeId, uninstall: impl FnOnce(&HookSlot) -> bool) -> bool {
+    let mut hooks = HOOKS.write().unwrap_or_else(PoisonError::into_inner);
+    let Some(slot) = hooks.get(&key) else {
+        return false;
+    };
+    // the hook is uninstalled before the slot is removed, trampolines that are still running
+    // afterwards fall back to the original function
+    let detached = uninstall(slot);
+    hooks.remove(&key);
+    detached
+}
+
+fn uninstall(env: &SdkEnv, slot: &HookSlot) -> bool {
+    match slot.kind {
+        HookKind::Detour => unsafe {
+            ((*env.sdk.hooking).Detach.unwrap())(env.handle, slot.target as VoidPtr)
+        },
+        HookKind::Native => restore_native(slot),
+    }
+}
+
+fn restore_native(slot: &HookSlot) -> bool {
+    let handler = unsafe { AtomicUsize::from_ptr(slot.target as *mut usize) };
+    handler.store(slot.original.load(Ordering::Acquire), Ordering::Release);
+    true
+}
+
+// returns the slot of the detour, if it's still attached, and the original function, the
+// trampolines run inside of `extern "C"` functions, so they must not panic
+#[inline]
+fn resolve<D: 'static>() -> (Option<Arc<HookSlot>>, usize) {
+    let key = TypeId::of::<D>();
+    // the lock is released before calling the detour, so that detours can freely call hooked
+    // functions recursively
+    let slot = HOOKS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&key)
+        .cloned();
+    let original = match &slot {
+        Some(slot) => slot.original.load(Ordering::Acquire),
+        // the original is saved before a trampoline can be reached, so it's always present
+        None => ORIGINALS
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+            .copied()
+            .unwrap_or_default(),
+    };
+    (slot, original)
+}
+
+extern "C" fn native_trampoline<D>(
+    ctx: Option<&IScriptable>,
+    frame: &mut StackFrame,
+    ret: VoidPtr,
+    unk: i64,
+) where
+    D: Fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, NativeHandler) + Send + Sync + 'static,
+{
+    let (slot, original) = resolve::<D>();
+    let func = unsafe { mem::transmute::<usize, RawNativeHandler>(original) };
+    match slot
+        .as_deref()
+        .and_then(|slot| slot.detour.downcast_ref::<D>())
+    {
+        Some(detour) => detour(ctx, frame, ret, NativeHandler { func, unk }),
+        None => func(ctx, frame, ret, unk),
+    }
+}
+
+/// The original native handler of an RTTI function, passed to native detours so that they can
+/// execute the original code.
+#[derive(Debug, Clone, Copy)]
+pub struct NativeHandler {
+    func: RawNativeHandler,
+    unk: i64,
+}
+
+impl NativeHandler {
+    /// Invokes the original native handler.
+    ///
+    /// # Safety
+    /// The arguments on the stack frame must not have been read before calling this function and
+    /// `ret` must point to a value of the return type of the function, or be null.
+    #[inline]
+    pub unsafe fn call(self, ctx: Option<&IScriptable>, frame: &mut StackFrame, ret: VoidPtr) {
+        (self.func)(ctx, frame, ret, self.unk)
+    }
+}
+
+/// A trait for functions that are convertible to pointers. Only non-closure functions can
+/// satisfy this requirement.
+#[sealed]
+pub trait FnPtr<Args, Ret>: Copy + 'static {
+    fn to_ptr(&self) -> VoidPtr;
+
+    #[doc(hidden)]
+    unsafe fn from_ptr(ptr: VoidPtr) -> Self;
+}
+
+/// A trait for functions and closures that can be used as detours for target functions of
+/// type `F`. The detour receives all the arguments of the target function followed by the
+/// original function, which can be called to execute the original code.
+#[sealed]
+pub trait Detour<F, Args, Ret>: Send + Sync + 'static
+where
+    F: FnPtr<Args, Ret>,
+{
+    #[doc(hidden)]
+    fn trampoline() -> F;
+}
+
+macro_rules! impl_fn_ptr {
+    ($($ty:ident),*) => {
+        #[sealed]
+        impl <$($ty: 'static,)* Ret: 'static> FnPtr<($($ty,)*), Ret> for unsafe extern "C" fn($($ty,)*) -> Ret {
+            #[inline]
+            fn to_ptr(&self) -> VoidPtr {
+                *self as _
+            }
+
+            #[inline]
+            unsafe fn from_ptr(ptr: VoidPtr) -> Self {
+                unsafe { mem::transmute::<VoidPtr, Self>(ptr) }
+            }
+        }
+
+        #[sealed]
+        impl <$($ty: 'static,)* Ret: 'static, FN> Detour<unsafe extern "C" fn($($ty,)*) -> Ret, ($($ty,)*), Ret> for FN
+        where
+            FN: Fn($($ty,)* unsafe extern "C" fn($($ty,)*) -> Ret) -> Ret + Send + Sync + 'static,
+        {
+            fn trampoline() -> unsafe extern "C" fn($($ty,)*) -> Ret {
+                #[allow(non_snake_case)]
+                unsafe extern "C" fn trampoline<$($ty: 'static,)* Ret: 'static, FN>($($ty: $ty,)*) -> Ret
+                where
+                    FN: Fn($($ty,)* unsafe extern "C" fn($($ty,)*) -> Ret) -> Ret + Send + Sync + 'static,
+                {
+                    let (slot, original) = resolve::<FN>();
+                    let original = unsafe {
+                        <unsafe extern "C" fn($($ty,)*) -> Ret as FnPtr<($($ty,)*), Ret>>::from_ptr(original as VoidPtr)
+                    };
+                    match slot.as_deref().and_then(|slot| slot.detour.downcast_ref::<FN>()) {
+                        Some(detour) => detour($($ty,)* original),
+                        None => original($($ty,)*),
+                    }
+                }
+
+                trampoline::<$($ty,)* Ret, FN>
+            }
+        }
+    }
+}
+
+impl_fn_ptr!();
+impl_fn_ptr!(A);
+impl_fn_ptr!(A, B);
+impl_fn_ptr!(A, B, C);
+impl_fn_ptr!(A, B, C, D);
+impl_fn_ptr!(A, B, C, D, E);
+impl_fn_ptr!(A, B, C, D, E, F);
+impl_fn_ptr!(A, B, C, D, E, F, G);
+impl_fn_ptr!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+    use std::mem;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{
+        detach_with, register, register_native, restore_native, HookError, HookKind, NativeHandler,
+        RawNativeHandler, HOOKS, ORIGINALS,
+    };
+    use crate::types::{IScriptable, StackFrame};
+    use crate::VoidPtr;
+
+    fn key_of<D: 'static>(_: &D) -> TypeId {
+        TypeId::of::<D>()
+    }
+
+    extern "C" fn original(
+        _ctx: Option<&IScriptable>,
+        _frame: &mut StackFrame,
+        ret: VoidPtr,
+        _: i64,
+    ) {
+        unsafe { *ret.cast::<i32>() = 1 };
+    }
+
+    fn call(handler: &AtomicUsize, ret: &mut i32) {
+        call_raw(handler.load(Ordering::Acquire), ret);
+    }
+
+    fn call_raw(handler: usize, ret: &mut i32) {
+        let handler = unsafe { mem::transmute::<usize, RawNativeHandler>(handler) };
+        let mut frame = unsafe { mem::zeroed::<StackFrame>() };
+        handler(None, &mut frame, ret as *mut i32 as VoidPtr, 0);
+    }
+
+    #[test]
+    fn register_and_detach() {
+        static TARGET: u8 = 0;
+        let target = &TARGET as *const u8 as usize;
+        let detour = || {};
+        let other = || {};
+        let key = key_of(&detour);
+
+        register(HookKind::Detour, target, detour, |_| true).unwrap();
+        assert!(HOOKS.read().unwrap().contains_key(&key));
+        assert!(ORIGINALS.read().unwrap().contains_key(&key));
+        assert!(matches!(
+            register(HookKind::Detour, target + 1, detour, |_| true),
+            Err(HookError::DetourInUse)
+        ));
+        assert!(matches!(
+            register(HookKind::Detour, target, other, |_| true),
+            Err(HookError::AlreadyHooked)
+        ));
+
+        assert!(detach_with(key, |_| true));
+        assert!(!HOOKS.read().unwrap().contains_key(&key));
+        assert!(!detach_with(key, |_| true));
+        // the target can be hooked again once the previous hook is detached
+        register(HookKind::Detour, target, other, |_| true).unwrap();
+        assert!(detach_with(key_of(&other), |_| true));
+    }
+
+    #[test]
+    fn failed_install() {
+        static TARGET: u8 = 0;
+        let detour = || {};
+        let key = key_of(&detour);
+
+        assert!(matches!(
+            register(
+                HookKind::Detour,
+                &TARGET as *const u8 as usize,
+                detour,
+                |_| false
+            ),
+            Err(HookError::AttachFailed)
+        ));
+        assert!(!HOOKS.read().unwrap().contains_key(&key));
+        assert!(!ORIGINALS.read().unwrap().contains_key(&key));
+    }
+
+    #[test]
+    fn native_detour() {
+        static HANDLER: AtomicUsize = AtomicUsize::new(0);
+        HANDLER.store(original as RawNativeHandler as usize, Ordering::Release);
+
+        let detour =
+            |ctx: Option<&IScriptable>, frame: &mut StackFrame, ret: VoidPtr, cb: NativeHandler| {
+                unsafe { cb.call(ctx, frame, ret) };
+                unsafe { *ret.cast::<i32>() += 1 };
+            };
+        let key = key_of(&detour);
+        register_native(HANDLER.as_ptr(), detour).unwrap();
+
+        let mut ret = 0;
+        call(&HANDLER, &mut ret);
+        assert_eq!(ret, 2);
+
+        assert!(detach_with(key, restore_native));
+        assert_eq!(
+            HANDLER.load(Ordering::Acquire),
+            original as RawNativeHandler as usize
+        );
+        call(&HANDLER, &mut ret);
+        assert_eq!(ret, 1);
+    }
+
+    #[test]
+    fn detached_trampoline_calls_original() {
+        static HANDLER: AtomicUsize = AtomicUsize::new(0);
+        HANDLER.store(original as RawNativeHandler as usize, Ordering::Release);
+
+        let detour =
+            |_: Option<&IScriptable>, _: &mut StackFrame, ret: VoidPtr, _: NativeHandler| {
+                unsafe { *ret.cast::<i32>() = 2 };
+            };
+        let key = key_of(&detour);
+        register_native(HANDLER.as_ptr(), detour).unwrap();
+        // a caller that loaded the trampoline before the hook was detached
+        let trampoline = HANDLER.load(Ordering::Acquire);
+        assert!(detach_with(key, restore_native));
+
+        let mut ret = 0;
+        call_raw(trampoline, &mut ret);
+        assert_eq!(ret, 1);
+    }
+}
@@ -1,3 +1,4 @@
+use std::any::Any;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem::MaybeUninit;
@@ -5,10 +6,11 @@ use std::mem::MaybeUninit;
 use sealed::sealed;
 use thiserror::Error;
 
-use crate::repr::{FromRepr, IntoRepr, NativeRepr};
+use crate::repr::{FromArg, IntoRepr, NativeRepr};
+use crate::systems::RttiSystem;
 use crate::types::{
     CName, ClassKind, Function, FunctionFlags, FunctionHandler, GlobalFunction, IScriptable,
-    Method, PoolRef, Ref, ScriptClass, StackArg, StackFrame,
+    Method, PoolRef, Ref, ScriptBacktrace, ScriptClass, StackArg, StackFrame, ValuePtr,
 };
 use crate::VoidPtr;
 
@@ -41,6 +43,60 @@ pub enum InvokeError {
     NullReceiver(&'static str),
 }
 
+/// Logs a panic that occurred in an exported function along with the script backtrace.
+#[doc(hidden)]
+#[cold]
+pub fn report_panic(name: &CStr, frame: &StackFrame, payload: Box<dyn Any + Send>) {
+    let msg = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+    let backtrace = ScriptBacktrace::capture(frame);
+    let name = name.to_string_lossy();
+
+    #[cfg(feature = "log")]
+    log::error!("native function '{name}' panicked: {msg}\nscript backtrace:\n{backtrace}");
+    #[cfg(not(feature = "log"))]
+    if let Some(env) = crate::plugin_env() {
+        env.error(format_args!(
+            "native function '{name}' panicked: {msg}\nscript backtrace:\n{backtrace}"
+        ));
+    }
+}
+
+/// Leaves the stack frame and the return value of an exported function that panicked in the
+/// state the game expects after a call. The arguments that haven't been read are skipped and
+/// the return value is default-constructed, since it may have never been written.
+///
+/// # Safety
+/// `ret` must be null or point to uninitialized storage for a value of type `ret_type`.
+#[doc(hidden)]
+#[cold]
+pub unsafe fn recover_from_panic(frame: &mut StackFrame, ret: VoidPtr, ret_type: CName) {
+    unsafe { frame.skip_to_param_end() };
+    if ret.is_null() {
+        return;
+    }
+    if let Some(type_) = RttiSystem::get().get_type(ret_type) {
+        unsafe { type_.construct(ValuePtr::new(ret)) };
+    }
+}
+
+/// Returns the name of the return type of an exported global function.
+#[doc(hidden)]
+#[inline]
+pub fn global_return_type<F: GlobalInvocable<A, R>, A, R>(_f: &F) -> CName {
+    F::FN_TYPE.ret
+}
+
+/// Returns the name of the return type of an exported method.
+#[doc(hidden)]
+#[inline]
+pub fn method_return_type<Ctx, F: MethodInvocable<Ctx, A, R>, A, R>(_f: &F) -> CName {
+    F::FN_TYPE.ret
+}
+
 /// A trait for functions that can be exported as global functions.
 #[sealed]
 pub trait GlobalInvocable<A, R> {
@@ -56,8 +112,8 @@ macro_rules! impl_global_invocable {
             #[sealed]
             impl<$($types,)* R, FN> GlobalInvocable<($($types,)*), R::Repr> for FN
             where
-                FN: Fn($($types,)*) -> R,
-                $($types: FromRepr, $types::Repr: Default,)*
+                FN: Fn($($types,)*) -> R + for<'a> Fn($($types::Rebind<'a>,)*) -> R,
+                $($types: FromArg,)*
                 R: IntoRepr
             {
                 const FN_TYPE: FnType = FnType {
@@ -67,7 +123,9 @@ macro_rules! impl_global_invocable {
 
                 #[inline]
                 fn invoke(self, _ctx: &IScriptable, frame: &mut StackFrame, ret: &mut MaybeUninit<R::Repr>) {
-                    $(let $types = $types::from_repr(unsafe { frame.get_arg::<$types::Repr>() });)*
+                    // the storage of borrowed arguments has to outlive the call
+                    $(let $types = &mut None;)*
+                    $(let $types = unsafe { $types::from_frame(frame, $types) };)*
                     let res = self($($types,)*);
                     unsafe { ret.as_mut_ptr().write(res.into_repr()) }
                 }
@@ -101,8 +159,8 @@ macro_rules! impl_method_invocable {
             #[sealed]
             impl<Ctx, $($types,)* R, FN> MethodInvocable<Ctx, ($($types,)*), R::Repr> for FN
             where
-                FN: Fn(&Ctx, $($types,)*) -> R,
-                $($types: FromRepr, $types::Repr: Default,)*
+                FN: Fn(&Ctx, $($types,)*) -> R + for<'a> Fn(&Ctx, $($types::Rebind<'a>,)*) -> R,
+                $($types: FromArg,)*
                 R: IntoRepr
             {
                 const FN_TYPE: FnType = FnType {
@@ -112,7 +170,9 @@ macro_rules! impl_method_invocable {
 
                 #[inline]
                 fn invoke(self, ctx: &Ctx, frame: &mut StackFrame, ret: &mut MaybeUninit<R::Repr>) {
-                    $(let $types = $types::from_repr(unsafe { frame.get_arg::<$types::Repr>() });)*
+                    // the storage of borrowed arguments has to outlive the call
+                    $(let $types = &mut None;)*
+                    $(let $types = unsafe { $types::from_frame(frame, $types) };)*
                     let res = self(ctx, $($types,)*);
                     unsafe { ret.as_mut_ptr().write(res.into_repr()) }
                 }
@@ -263,7 +323,14 @@ macro_rules! global {
             _unk: i64,
         ) {
             let out = unsafe { std::mem::transmute(ret) };
-            $crate::GlobalInvocable::invoke($fun, ctx, frame, out);
+            let res = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                $crate::GlobalInvocable::invoke($fun, ctx, frame, out)
+            }));
+            if let Err(payload) = res {
+                $crate::internal::report_panic($name, frame, payload);
+                let ret_type = $crate::internal::global_return_type(&$fun);
+                unsafe { $crate::internal::recover_from_panic(frame, ret, ret_type) };
+            }
             unsafe { frame.step() };
         }
 
@@ -283,7 +350,14 @@ macro_rules! method {
             _unk: i64,
         ) {
             let out = unsafe { ::std::mem::transmute(ret) };
-            $crate::MethodInvocable::invoke($ty::$id, ctx, frame, out);
+            let res = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                $crate::MethodInvocable::invoke($ty::$id, ctx, frame, out)
+            }));
+            if let Err(payload) = res {
+                $crate::internal::report_panic($name, frame, payload);
+                let ret_type = $crate::internal::method_return_type::<$ty, _, _, _>(&$ty::$id);
+                unsafe { $crate::internal::recover_from_panic(frame, ret, ret_type) };
+            }
             unsafe { frame.step() };
         }
 
@@ -416,3 +490,31 @@ impl_args!(
     (A, B, C, D, E),
     (A, B, C, D, E, F)
 );
+
+#[cfg(test)]
+mod tests {
+    use super::{global_return_type, GlobalInvocable};
+    use crate::types::{CName, RedArray, RedString};
+
+    fn arg_types<F: GlobalInvocable<A, R>, A, R>(_f: &F) -> &'static [CName] {
+        F::FN_TYPE.args
+    }
+
+    #[test]
+    fn borrowed_args() {
+        fn func(_a: &[i32], _b: &RedArray<f32>, _c: &RedString, _d: &str) -> i32 {
+            0
+        }
+
+        assert_eq!(
+            arg_types(&func),
+            [
+                CName::new("array:Int32"),
+                CName::new("array:Float"),
+                CName::new("String"),
+                CName::new("String")
+            ]
+        );
+        assert_eq!(global_return_type(&func), CName::new("Int32"));
+    }
+}
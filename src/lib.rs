@@ -7,10 +7,14 @@ use std::{ffi, fmt, mem};
 pub use export::{ClassExport, ExportList, ExportNil, Exportable, GlobalExport};
 use raw::root::{versioning, RED4ext as red};
 use sealed::sealed;
+use types::{CName, IScriptable, OpcodeHandler, StackFrame};
 pub use widestring::{widecstr as wcstr, U16CStr};
 
 mod export;
+mod hook;
 mod invocable;
+mod observer;
+mod opcode;
 mod raw;
 mod repr;
 mod systems;
@@ -18,11 +22,14 @@ mod systems;
 /// A module encapsulating various types defined in the RED4ext SDK.
 pub mod types;
 
+pub use hook::{Detour, FnPtr, HookError, HookGuard, NativeHandler};
 pub use invocable::{
     FnType, GlobalInvocable, GlobalMetadata, InvokeError, MethodInvocable, MethodMetadata, Receiver,
 };
-pub use repr::{FromRepr, IntoRepr, NativeRepr};
-pub use systems::{RttiRegistrator, RttiSystem, RttiSystemMut};
+pub use observer::{CallObserver, ObserverGuard, ScriptCall};
+pub use opcode::OpcodeGuard;
+pub use repr::{FromArg, FromRepr, IntoRepr, NativeRepr};
+pub use systems::{RttiRegistrator, RttiSystem, RttiSystemMut, TweakDb, TweakDbError};
 
 /// Hashes of known function addresses.
 ///
@@ -46,6 +53,9 @@ pub mod hashes {
 
 #[doc(hidden)]
 pub mod internal {
+    pub use crate::invocable::{
+        global_return_type, method_return_type, recover_from_panic, report_panic,
+    };
     pub use crate::red::{EMainReason, PluginHandle, PluginInfo, Sdk};
 }
 
@@ -78,6 +88,27 @@ pub trait Plugin {
     fn on_init(_env: &SdkEnv) {}
 }
 
+// there can only be one plugin per library, so the environment is shared crate-wide
+static ENV: OnceLock<Box<SdkEnv>> = OnceLock::new();
+
+/// Returns the environment of the plugin, if it has been initialized.
+#[inline]
+pub(crate) fn plugin_env() -> Option<&'static SdkEnv> {
+    ENV.get().map(|env| &**env)
+}
+
+// plugins are loaded on the main thread, so its ID is recorded on initialization
+static MAIN_THREAD: OnceLock<std::thread::ThreadId> = OnceLock::new();
+
+/// Returns `true` if the current thread is the main thread of the game. Always returns `false`
+/// before the plugin is initialized.
+#[inline]
+pub(crate) fn is_main_thread() -> bool {
+    MAIN_THREAD
+        .get()
+        .is_some_and(|id| *id == std::thread::current().id())
+}
+
 /// A set of useful operations that can be performed on a plugin.
 #[sealed]
 pub trait PluginOps: Plugin {
@@ -91,6 +122,8 @@ pub trait PluginOps: Plugin {
     fn info() -> PluginInfo;
     #[doc(hidden)]
     fn init(env: SdkEnv);
+    #[doc(hidden)]
+    fn deinit();
 }
 
 #[sealed]
@@ -104,7 +137,6 @@ where
 
     #[inline]
     fn env_lock() -> &'static OnceLock<Box<SdkEnv>> {
-        static ENV: OnceLock<Box<SdkEnv>> = OnceLock::new();
         &ENV
     }
 
@@ -123,6 +155,7 @@ where
         Self::env_lock()
             .set(Box::new(env))
             .expect("plugin environment should not be initialized");
+        MAIN_THREAD.set(std::thread::current().id()).ok();
 
         #[cfg(feature = "log")]
         {
@@ -132,6 +165,14 @@ where
 
         Self::on_init(Self::env());
     }
+
+    fn deinit() {
+        types::stop_pending_loads();
+        opcode::restore_all();
+        if let Some(env) = Self::env_lock().get() {
+            hook::detach_all(env);
+        }
+    }
 }
 
 /// Exports a set of necessary DLL entry points for RED4ext to load the plugin. Your plugin will
@@ -155,8 +196,19 @@ macro_rules! export_plugin {
                 reason: $crate::internal::EMainReason::Type,
                 sdk: $crate::internal::Sdk,
             ) {
-                <$trait as $crate::PluginOps>::init($crate::SdkEnv::new(handle, sdk));
-                $crate::systems::RttiRegistrator::add(Some(on_register), Some(on_post_register));
+                match reason {
+                    $crate::internal::EMainReason::Load => {
+                        <$trait as $crate::PluginOps>::init($crate::SdkEnv::new(handle, sdk));
+                        $crate::systems::RttiRegistrator::add(
+                            Some(on_register),
+                            Some(on_post_register),
+                        );
+                    }
+                    $crate::internal::EMainReason::Unload => {
+                        <$trait as $crate::PluginOps>::deinit();
+                    }
+                    _ => {}
+                }
             }
 
             #[no_mangle]
@@ -323,59 +375,229 @@ impl SdkEnv {
     }
 
     /// Attaches a hook to a target function.
-    /// The hook will be called instead of the target function. The hook must accept a callback
-    /// function as its last argument, which should be called to execute the original function.
+    /// The detour will be called instead of the target function. The detour receives all the
+    /// arguments of the target followed by the original function, which can be called to
+    /// execute the original code. Closures can be used as detours, but their parameter types
+    /// have to be annotated.
     ///
-    /// # Safety
-    /// The target and detour functions must both be valid and compatible function pointers.
+    /// The hook stays attached for as long as the returned guard is alive. Each detour type can
+    /// only be attached once and the same target cannot be hooked twice at the same time.
     ///
     /// # Example
     /// ```rust
-    /// use red4rs::{hooks, SdkEnv};
+    /// use red4rs::{HookError, HookGuard, SdkEnv};
     ///
-    /// hooks! {
-    ///    static ADD_HOOK: fn(a: u32, b: u32) -> u32;
+    /// fn attach_my_hook(
+    ///     env: &SdkEnv,
+    ///     addr: unsafe extern "C" fn(u32, u32) -> u32,
+    /// ) -> Result<HookGuard<'_>, HookError> {
+    ///     env.hook(addr, detour)
     /// }
     ///
-    /// fn attach_my_hook(env: &SdkEnv, addr: unsafe extern "C" fn(u32, u32) -> u32) {
-    ///     unsafe { env.attach_hook(ADD_HOOK, addr, detour) };
+    /// fn detour(a: u32, b: u32, cb: unsafe extern "C" fn(u32, u32) -> u32) -> u32 {
+    ///     // do something here...
+    ///     unsafe { cb(a, b) }
     /// }
+    /// ```
+    #[inline]
+    pub fn hook<F, A, R, D>(&self, target: F, detour: D) -> Result<HookGuard<'_>, HookError>
+    where
+        F: FnPtr<A, R>,
+        D: Detour<F, A, R>,
+    {
+        hook::attach(self, target, detour)
+    }
+
+    /// Attaches a hook to a function whose address is resolved from a hash.
+    /// See [`SdkEnv::hook`] for more information.
+    ///
+    /// # Safety
+    /// The target function type `F` must match the signature of the function at the address.
     ///
-    /// unsafe extern "C" fn detour(a: u32, b: u32, cb: unsafe extern "C" fn(u32, u32) -> u32) -> u32 {
+    /// # Example
+    /// ```rust
+    /// use red4rs::{hashes, HookError, HookGuard, SdkEnv};
+    ///
+    /// fn attach_my_hook(env: &SdkEnv) -> Result<HookGuard<'_>, HookError> {
+    ///     unsafe { env.attach_hook_by_hash(hashes::CBaseFunction_ExecuteNative, detour) }
+    /// }
+    ///
+    /// fn detour(
+    ///     func: usize,
+    ///     ctx: usize,
+    ///     frame: usize,
+    ///     cb: unsafe extern "C" fn(usize, usize, usize) -> bool,
+    /// ) -> bool {
     ///     // do something here...
-    ///     cb(a, b)
+    ///     unsafe { cb(func, ctx, frame) }
     /// }
     /// ```
-    pub unsafe fn attach_hook<F1, A1, R1, F2, A2, R2>(
+    pub unsafe fn attach_hook_by_hash<F, A, R, D>(
         &self,
-        hook: *mut Hook<F1, F2>,
-        target: F1,
-        detour: F2,
-    ) -> bool
+        hash: u32,
+        detour: D,
+    ) -> Result<HookGuard<'_>, HookError>
     where
-        F1: FnPtr<A1, R1>,
-        F2: FnPtr<A2, R2>,
+        F: FnPtr<A, R>,
+        D: Detour<F, A, R>,
     {
-        unsafe {
-            let Hook(original, cb_ref, detour_ref) = &*hook;
-            detour_ref.replace(Some(detour));
-
-            ((*self.sdk.hooking).Attach.unwrap())(
-                self.handle,
-                target.to_ptr(),
-                original.to_ptr(),
-                (*cb_ref).cast::<VoidPtr>(),
-            )
+        let addr = hashes::resolve(hash);
+        if addr == 0 {
+            return Err(HookError::UnresolvedHash(hash));
         }
+        self.hook(unsafe { F::from_ptr(addr as VoidPtr) }, detour)
     }
 
-    /// Detaches a hook from a target function.
-    #[inline]
-    pub unsafe fn detach_hook<F, A, R>(&self, target: F) -> bool
+    /// Replaces the native handler of a global RTTI function with a detour.
+    /// The detour receives the context, which is [`None`] for global functions, the stack frame
+    /// and a pointer to the return value, followed by the original handler, which can be called
+    /// to execute the original code.
+    /// The original handler is restored when the returned guard is dropped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use red4rs::types::{CName, IScriptable, StackFrame};
+    /// use red4rs::{HookError, HookGuard, NativeHandler, SdkEnv, VoidPtr};
+    ///
+    /// fn attach_my_hook(env: &SdkEnv) -> Result<HookGuard<'_>, HookError> {
+    ///     env.hook_native_function(CName::new("OperatorAdd;Int32Int32;Int32"), detour)
+    /// }
+    ///
+    /// fn detour(ctx: Option<&IScriptable>, frame: &mut StackFrame, ret: VoidPtr, cb: NativeHandler) {
+    ///     // do something here...
+    ///     unsafe { cb.call(ctx, frame, ret) }
+    /// }
+    /// ```
+    pub fn hook_native_function<D>(
+        &self,
+        name: CName,
+        detour: D,
+    ) -> Result<HookGuard<'_>, HookError>
     where
-        F: FnPtr<A, R>,
+        D: Fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, NativeHandler)
+            + Send
+            + Sync
+            + 'static,
     {
-        unsafe { ((*self.sdk.hooking).Detach.unwrap())(self.handle, target.to_ptr()) }
+        let rtti = RttiSystem::get();
+        let func = rtti.get_function(name).ok_or(HookError::FunctionNotFound)?;
+        hook::attach_native(self, func, detour)
+    }
+
+    /// Replaces the native handler of an RTTI method with a detour.
+    /// See [`SdkEnv::hook_native_function`] for more information.
+    pub fn hook_native_method<D>(
+        &self,
+        class: CName,
+        name: CName,
+        detour: D,
+    ) -> Result<HookGuard<'_>, HookError>
+    where
+        D: Fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, NativeHandler)
+            + Send
+            + Sync
+            + 'static,
+    {
+        let rtti = RttiSystem::get();
+        let method = rtti
+            .get_class(class)
+            .and_then(|class| class.get_method(name))
+            .ok_or(HookError::FunctionNotFound)?;
+        hook::attach_native(self, method.as_function(), detour)
+    }
+
+    /// Replaces the handler of a bytecode opcode. The handler receives the stack frame with
+    /// the code positioned right after the opcode. The previous handler is restored when the
+    /// returned guard is dropped or when the plugin is unloaded.
+    pub fn replace_opcode_handler(
+        &self,
+        opcode: u8,
+        handler: OpcodeHandler,
+    ) -> Result<OpcodeGuard, HookError> {
+        opcode::replace(opcode, handler)
+    }
+
+    /// Wraps the handler of a bytecode opcode with a detour. The detour receives the arguments
+    /// of the handler followed by the handler that was in place before, which can be called to
+    /// execute the opcode. Multiple detours can be chained on the same opcode.
+    ///
+    /// # Example
+    /// ```rust
+    /// use red4rs::types::bytecode::Return;
+    /// use red4rs::types::{IScriptable, Instr, OpcodeHandler, StackFrame};
+    /// use red4rs::{HookError, OpcodeGuard, SdkEnv, VoidPtr};
+    ///
+    /// fn trace_returns(env: &SdkEnv) -> Result<OpcodeGuard, HookError> {
+    ///     env.wrap_opcode_handler(Return::OPCODE, on_return)
+    /// }
+    ///
+    /// fn on_return(
+    ///     ctx: Option<&IScriptable>,
+    ///     frame: &mut StackFrame,
+    ///     out: VoidPtr,
+    ///     a4: VoidPtr,
+    ///     next: OpcodeHandler,
+    /// ) {
+    ///     // do something here...
+    ///     unsafe { next(ctx, frame, out, a4) }
+    /// }
+    /// ```
+    pub fn wrap_opcode_handler<D>(&self, opcode: u8, detour: D) -> Result<OpcodeGuard, HookError>
+    where
+        D: Fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, VoidPtr, OpcodeHandler)
+            + Send
+            + Sync
+            + 'static,
+    {
+        opcode::wrap(opcode, detour)
+    }
+
+    /// Registers an observer for calls to a global scripted function. The observer is notified
+    /// every time the function is called from scripts, until the returned guard is dropped.
+    /// Functions with out parameters cannot be observed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use red4rs::types::CName;
+    /// use red4rs::{CallObserver, HookError, ObserverGuard, ScriptCall, SdkEnv};
+    ///
+    /// fn observe_my_function(env: &SdkEnv) -> Result<ObserverGuard, HookError> {
+    ///     env.observe_function(CName::new("MyFunction;Int32;Int32"), Logger)
+    /// }
+    ///
+    /// struct Logger;
+    ///
+    /// impl CallObserver for Logger {
+    ///     fn before(&self, call: &mut ScriptCall<'_>) {
+    ///         let arg = call.arg::<i32>(0);
+    ///         // do something here...
+    ///     }
+    /// }
+    /// ```
+    pub fn observe_function<O: CallObserver>(
+        &self,
+        name: CName,
+        observer: O,
+    ) -> Result<ObserverGuard, HookError> {
+        let rtti = RttiSystem::get();
+        let func = rtti.get_function(name).ok_or(HookError::FunctionNotFound)?;
+        observer::observe(func, observer)
+    }
+
+    /// Registers an observer for calls to a scripted method.
+    /// See [`SdkEnv::observe_function`] for more information.
+    pub fn observe_method<O: CallObserver>(
+        &self,
+        class: CName,
+        name: CName,
+        observer: O,
+    ) -> Result<ObserverGuard, HookError> {
+        let rtti = RttiSystem::get();
+        let method = rtti
+            .get_class(class)
+            .and_then(|class| class.get_method(name))
+            .ok_or(HookError::FunctionNotFound)?;
+        observer::observe(method.as_function(), observer)
     }
 }
 
@@ -464,87 +686,6 @@ impl From<ApiVersion> for u32 {
     }
 }
 
-/// Defines a set of hooks that can be attached to target functions.
-/// The hooks are defined as static variables and must be initialized with a call to
-/// [`SdkEnv::attach_hook`].
-///
-/// # Example
-/// ```rust
-/// use red4rs::hooks;
-///
-/// hooks! {
-///    static ADD_HOOK: fn(a: u32, b: u32) -> u32;
-/// }
-/// ```
-#[macro_export]
-macro_rules! hooks {
-    ($(static $name:ident: fn($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {$(
-        static mut $name: *mut $crate::Hook<
-            unsafe extern "C" fn($($arg: $ty),*) -> $ret,
-            unsafe extern "C" fn($($arg: $ty),*, cb: unsafe extern "C" fn($($arg: $ty),*) -> $ret) -> $ret
-        > = unsafe {
-
-            static mut TARGET: Option<unsafe extern "C" fn($($arg: $ty),*) -> $ret> = None;
-            static mut DETOUR: Option<unsafe extern "C" fn($($arg: $ty),*, cb: unsafe extern "C" fn($($arg: $ty),*) -> $ret) -> $ret> = None;
-
-            unsafe extern "C" fn internal($($arg: $ty),*) -> $ret {
-                let target = unsafe { TARGET.expect("target function should be set") };
-                let detour = unsafe { DETOUR.expect("detour function should be set") };
-                detour($($arg,)* target)
-            }
-
-            static mut HOOK: $crate::Hook<
-                unsafe extern "C" fn($($arg: $ty),*) -> $ret,
-                unsafe extern "C" fn($($arg: $ty),*, cb: unsafe extern "C" fn($($arg: $ty),*) -> $ret) -> $ret
-            > = unsafe { $crate::Hook::new(internal, ::std::ptr::addr_of_mut!(TARGET), ::std::ptr::addr_of_mut!(DETOUR)) };
-
-            ::std::ptr::addr_of_mut!(HOOK)
-        };
-    )*};
-}
-
-/// A wrapper around function pointers that can be passed to [`SdkEnv::attach_hook`] to install
-/// detours.
-#[derive(Debug)]
-pub struct Hook<O, R>(O, *mut Option<O>, *mut Option<R>);
-
-#[doc(hidden)]
-impl<O, R> Hook<O, R> {
-    #[inline]
-    pub const fn new(original: O, cb_ref: *mut Option<O>, detour_ref: *mut Option<R>) -> Self {
-        Self(original, cb_ref, detour_ref)
-    }
-}
-
-/// A trait for functions that are convertible to pointers. Only non-closure functions can
-/// satisfy this requirement.
-#[sealed]
-pub trait FnPtr<Args, Ret> {
-    fn to_ptr(&self) -> VoidPtr;
-}
-
-macro_rules! impl_fn_ptr {
-    ($($ty:ident),*) => {
-        #[sealed]
-        impl <$($ty,)* Ret> FnPtr<($($ty,)*), Ret> for unsafe extern "C" fn($($ty,)*) -> Ret {
-            #[inline]
-            fn to_ptr(&self) -> VoidPtr {
-                *self as _
-            }
-        }
-    }
-}
-
-impl_fn_ptr!();
-impl_fn_ptr!(A);
-impl_fn_ptr!(A, B);
-impl_fn_ptr!(A, B, C);
-impl_fn_ptr!(A, B, C, D);
-impl_fn_ptr!(A, B, C, D, E);
-impl_fn_ptr!(A, B, C, D, E, F);
-impl_fn_ptr!(A, B, C, D, E, F, G);
-impl_fn_ptr!(A, B, C, D, E, F, G, H);
-
 /// A callback function to be called when a state is entered, updated, or exited.
 pub type StateHandler = unsafe extern "C" fn(app: &GameApp);
 
@@ -631,11 +772,17 @@ impl PluginInfo {
 }
 
 const fn fnv1a64(str: &str) -> u64 {
-    const PRIME: u64 = 0x0100_0000_01b3;
     const SEED: u64 = 0xCBF2_9CE4_8422_2325;
 
-    let mut tail = str.as_bytes();
-    let mut hash = SEED;
+    fnv1a64_seeded(str.as_bytes(), SEED)
+}
+
+// continues hashing from a previous hash, which allows hashing strings incrementally
+const fn fnv1a64_seeded(bytes: &[u8], seed: u64) -> u64 {
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let mut tail = bytes;
+    let mut hash = seed;
     loop {
         match tail.split_first() {
             Some((head, rem)) => {
@@ -649,10 +796,14 @@ const fn fnv1a64(str: &str) -> u64 {
 }
 
 const fn fnv1a32(str: &str) -> u32 {
+    fnv1a32_bytes(str.as_bytes())
+}
+
+const fn fnv1a32_bytes(bytes: &[u8]) -> u32 {
     const PRIME: u32 = 0x0100_0193;
     const SEED: u32 = 0x811C_9DC5;
 
-    let mut tail = str.as_bytes();
+    let mut tail = bytes;
     let mut hash = SEED;
     loop {
         match tail.split_first() {
@@ -666,6 +817,18 @@ const fn fnv1a32(str: &str) -> u32 {
     }
 }
 
+// a non-cryptographic random number, seeded from the randomly keyed std hasher
+fn random_u64() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
 fn truncated_cstring(mut s: String) -> ffi::CString {
     s.truncate(s.find('\0').unwrap_or(s.len()));
     unsafe { CString::from_vec_unchecked(s.into_bytes()) }
@@ -0,0 +1,449 @@
+use std::alloc::{self, Layout};
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use crate::hook::HookError;
+use crate::opcode::{self, OpcodeGuard};
+use crate::repr::{FromRepr, IntoRepr, NativeRepr};
+use crate::types::{
+    CName, Function, IScriptable, Instr, InvokeStatic, InvokeVirtual, OpcodeHandler, StackArg,
+    StackFrame, Type, ValuePtr, CALL_INSTR_SIZE, OPCODE_SIZE,
+};
+use crate::VoidPtr;
+
+// observers keyed by the address of the observed function
+static OBSERVERS: RwLock<BTreeMap<usize, Vec<ObserverEntry>>> = RwLock::new(BTreeMap::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+// the observed functions and their names, calls that miss both are skipped without taking
+// any locks
+static OBSERVED_FUNCS: ObservedSet = ObservedSet::new();
+static OBSERVED_NAMES: ObservedSet = ObservedSet::new();
+
+// handlers of the invoke opcodes, these are installed with the first observer and stay in place
+// until the plugin is unloaded
+static HANDLERS: Mutex<Option<[OpcodeGuard; 2]>> = Mutex::new(None);
+static NEXT_INVOKE_STATIC: AtomicUsize = AtomicUsize::new(0);
+static NEXT_INVOKE_VIRTUAL: AtomicUsize = AtomicUsize::new(0);
+
+/// An observer of calls to a scripted function. Observers are notified before and after the
+/// function is executed and they can inspect the arguments and override the return value.
+///
+/// # Example
+/// ```rust
+/// use red4rs::{CallObserver, ScriptCall};
+///
+/// struct DoubleResult;
+///
+/// impl CallObserver for DoubleResult {
+///     fn after(&self, call: &mut ScriptCall<'_>) {
+///         if let Some(val) = call.ret::<i32>() {
+///             call.set_return(val * 2);
+///         }
+///     }
+/// }
+/// ```
+pub trait CallObserver: Send + Sync + 'static {
+    /// Called before the function is executed. Setting the return value here will cause the
+    /// function to be skipped.
+    fn before(&self, _call: &mut ScriptCall<'_>) {}
+
+    /// Called after the function is executed.
+    fn after(&self, _call: &mut ScriptCall<'_>) {}
+}
+
+/// A call to a scripted function, seen by [`CallObserver`]s.
+#[derive(Debug)]
+pub struct ScriptCall<'a> {
+    func: &'a Function,
+    ctx: Option<&'a IScriptable>,
+    args: &'a [Buffer<'a>],
+    ret: Option<&'a Buffer<'a>>,
+    overridden: bool,
+}
+
+impl<'a> ScriptCall<'a> {
+    /// Returns the function being called.
+    #[inline]
+    pub fn function(&self) -> &'a Function {
+        self.func
+    }
+
+    /// Returns the context of the call, the `this` pointer.
+    #[inline]
+    pub fn context(&self) -> Option<&'a IScriptable> {
+        self.ctx
+    }
+
+    /// Returns the number of arguments passed to the function.
+    #[inline]
+    pub fn arg_count(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Retrieves a copy of the argument at the specified index. Returns `None` if the index is
+    /// out of bounds or the argument is not of type `T`.
+    #[inline]
+    pub fn arg<T>(&self, index: usize) -> Option<T>
+    where
+        T: FromRepr,
+        T::Repr: Default,
+    {
+        self.args.get(index)?.get()
+    }
+
+    /// Retrieves a copy of the return value. Returns `None` if the function returns nothing or
+    /// the return value is not of type `T`.
+    #[inline]
+    pub fn ret<T>(&self) -> Option<T>
+    where
+        T: FromRepr,
+        T::Repr: Default,
+    {
+        self.ret?.get()
+    }
+
+    /// Overrides the return value of the function. Returns `false` if the function returns
+    /// nothing or the return value is not of type `T`.
+    #[inline]
+    pub fn set_return<T: IntoRepr>(&mut self, val: T) -> bool {
+        let success = self.ret.is_some_and(|ret| ret.set(val));
+        self.overridden |= success;
+        success
+    }
+}
+
+/// A handle to a registered [`CallObserver`]. The observer is removed when the guard is dropped.
+#[derive(Debug)]
+#[must_use = "the observer is removed when the guard is dropped"]
+pub struct ObserverGuard {
+    key: usize,
+    names: [CName; 2],
+    id: u64,
+}
+
+impl Drop for ObserverGuard {
+    fn drop(&mut self) {
+        let mut observers = OBSERVERS.write().unwrap_or_else(PoisonError::into_inner);
+        let Some(entries) = observers.get_mut(&self.key) else {
+            return;
+        };
+        let Some(index) = entries.iter().position(|(id, _)| *id == self.id) else {
+            return;
+        };
+        entries.remove(index);
+        if entries.is_empty() {
+            observers.remove(&self.key);
+        }
+        OBSERVED_FUNCS.remove(self.key as u64);
+        for name in self.names {
+            OBSERVED_NAMES.remove(name.into());
+        }
+    }
+}
+
+pub(crate) fn observe<O: CallObserver>(
+    func: &Function,
+    observer: O,
+) -> Result<ObserverGuard, HookError> {
+    if func.params().iter().any(|param| param.flags().is_out()) {
+        return Err(HookError::OutParams);
+    }
+    install_handlers()?;
+
+    // virtual calls refer to methods by name, both names are tracked, since either one can be
+    // used to look the method up
+    let names = [func.name(), func.short_name()];
+    Ok(register(
+        func as *const _ as usize,
+        names,
+        Arc::new(observer),
+    ))
+}
+
+fn install_handlers() -> Result<(), HookError> {
+    let mut handlers = HANDLERS.lock().unwrap_or_else(PoisonError::into_inner);
+    if handlers.is_none() {
+        let invoke_static =
+            opcode::chain(InvokeStatic::OPCODE, invoke_static, &NEXT_INVOKE_STATIC)?;
+        let invoke_virtual =
+            opcode::chain(InvokeVirtual::OPCODE, invoke_virtual, &NEXT_INVOKE_VIRTUAL)?;
+        *handlers = Some([invoke_static, invoke_virtual]);
+    }
+    Ok(())
+}
+
+fn register(key: usize, names: [CName; 2], observer: Arc<dyn CallObserver>) -> ObserverGuard {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut observers = OBSERVERS.write().unwrap_or_else(PoisonError::into_inner);
+    observers.entry(key).or_default().push((id, observer));
+    OBSERVED_FUNCS.insert(key as u64);
+    for name in names {
+        OBSERVED_NAMES.insert(name.into());
+    }
+    ObserverGuard { key, names, id }
+}
+
+unsafe extern "C" fn invoke_static(
+    ctx: Option<&IScriptable>,
+    frame: &mut StackFrame,
+    out: VoidPtr,
+    a4: VoidPtr,
+) {
+    let func = unsafe { frame.instr_at::<InvokeStatic>(-OPCODE_SIZE) }
+        .map(|instr| instr.func)
+        .filter(|&func| OBSERVED_FUNCS.contains(func as u64))
+        .and_then(|func| unsafe { func.as_ref() });
+    match func.and_then(|func| Some((func, observers_of(func)?))) {
+        Some((func, observers)) => unsafe { invoke_observed(func, ctx, frame, out, &observers) },
+        None => unsafe { next(&NEXT_INVOKE_STATIC)(ctx, frame, out, a4) },
+    }
+}
+
+unsafe extern "C" fn invoke_virtual(
+    ctx: Option<&IScriptable>,
+    frame: &mut StackFrame,
+    out: VoidPtr,
+    a4: VoidPtr,
+) {
+    let name = unsafe { frame.instr_at::<InvokeVirtual>(-OPCODE_SIZE) }
+        .map(|instr| instr.name)
+        .filter(|&name| OBSERVED_NAMES.contains(name.into()));
+    // the context is not tied to the lifetime of the frame
+    let this = ctx.or_else(|| unsafe { frame.context().map(|ctx| &*(ctx as *const IScriptable)) });
+    let func = name
+        .zip(this)
+        .and_then(|(name, this)| this.class().get_method(name))
+        .map(|method| method.as_function());
+    match func.and_then(|func| Some((func, observers_of(func)?))) {
+        Some((func, observers)) => unsafe { invoke_observed(func, this, frame, out, &observers) },
+        None => unsafe { next(&NEXT_INVOKE_VIRTUAL)(ctx, frame, out, a4) },
+    }
+}
+
+#[inline]
+fn next(handler: &AtomicUsize) -> OpcodeHandler {
+    unsafe { mem::transmute::<usize, OpcodeHandler>(handler.load(Ordering::Acquire)) }
+}
+
+#[inline]
+fn observers_of(func: &Function) -> Option<Vec<Arc<dyn CallObserver>>> {
+    observers_at(func as *const _ as usize)
+}
+
+fn observers_at(key: usize) -> Option<Vec<Arc<dyn CallObserver>>> {
+    let observers = OBSERVERS.read().unwrap_or_else(PoisonError::into_inner);
+    let entries = observers.get(&key)?;
+    Some(entries.iter().map(|(_, obs)| obs.clone()).collect())
+}
+
+type ObserverEntry = (u64, Arc<dyn CallObserver>);
+
+// counts the observers of keys that fall into each bucket, it can report a key as observed when
+// it's not, but never the other way around
+struct ObservedSet([AtomicU32; 256]);
+
+impl ObservedSet {
+    const fn new() -> Self {
+        Self([const { AtomicU32::new(0) }; 256])
+    }
+
+    #[inline]
+    fn insert(&self, key: u64) {
+        self.bucket(key).fetch_add(1, Ordering::Release);
+    }
+
+    #[inline]
+    fn remove(&self, key: u64) {
+        self.bucket(key).fetch_sub(1, Ordering::Release);
+    }
+
+    #[inline]
+    fn contains(&self, key: u64) -> bool {
+        self.bucket(key).load(Ordering::Acquire) != 0
+    }
+
+    #[inline]
+    fn bucket(&self, key: u64) -> &AtomicU32 {
+        // fibonacci hashing, the top bits are the best mixed
+        let index = key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 56;
+        &self.0[index as usize]
+    }
+}
+
+unsafe fn invoke_observed(
+    func: &Function,
+    ctx: Option<&IScriptable>,
+    frame: &mut StackFrame,
+    out: VoidPtr,
+    observers: &[Arc<dyn CallObserver>],
+) {
+    let args: Vec<Buffer<'_>> = func
+        .params()
+        .iter()
+        .map(|param| Buffer::new(param.type_()))
+        .collect();
+    let ret = func.return_type().map(Buffer::new);
+
+    unsafe {
+        frame.skip(CALL_INSTR_SIZE);
+        frame.read_call_args(args.iter().map(|arg| arg.ptr));
+    }
+
+    let mut call = ScriptCall {
+        func,
+        ctx,
+        args: &args,
+        ret: ret.as_ref(),
+        overridden: false,
+    };
+    for observer in observers {
+        observer.before(&mut call);
+    }
+
+    if !call.overridden {
+        let stack_args: Vec<StackArg<'_>> = args.iter().map(Buffer::as_stack_arg).collect();
+        let mut stack_ret = ret.as_ref().map(Buffer::as_stack_arg);
+        let result = func.execute_internal(ctx, &stack_args, stack_ret.as_mut());
+        #[cfg(feature = "log")]
+        if let Err(err) = result {
+            log::error!("Observed call error: {}", err);
+        }
+        #[cfg(not(feature = "log"))]
+        let _ = result;
+    }
+
+    for observer in observers {
+        observer.after(&mut call);
+    }
+
+    if let Some(ret) = &ret {
+        if !out.is_null() {
+            unsafe { ret.type_.assign(ValuePtr::new(out), ValuePtr::new(ret.ptr)) };
+        }
+    }
+}
+
+// a temporary, type-erased value of an RTTI type
+#[derive(Debug)]
+struct Buffer<'a> {
+    type_: &'a Type,
+    ptr: VoidPtr,
+    layout: Layout,
+}
+
+impl<'a> Buffer<'a> {
+    fn new(type_: &'a Type) -> Self {
+        let layout = Layout::from_size_align(
+            type_.size().max(1) as usize,
+            type_.alignment().max(1) as usize,
+        )
+        .expect("RTTI types should have a valid layout");
+        let ptr: VoidPtr = unsafe { alloc::alloc_zeroed(layout) }.cast();
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        unsafe { type_.construct(ValuePtr::new(ptr)) };
+        Self { type_, ptr, layout }
+    }
+
+    fn get<T>(&self) -> Option<T>
+    where
+        T: FromRepr,
+        T::Repr: Default,
+    {
+        if self.type_.name() != CName::new(T::Repr::NAME) {
+            return None;
+        }
+        let mut out = T::Repr::default();
+        let dst = ValuePtr::new(&mut out as *mut T::Repr as VoidPtr);
+        unsafe { self.type_.assign(dst, ValuePtr::new(self.ptr)) };
+        Some(T::from_repr(out))
+    }
+
+    fn set<T: IntoRepr>(&self, val: T) -> bool {
+        if self.type_.name() != CName::new(T::Repr::NAME) {
+            return false;
+        }
+        let mut val = val.into_repr();
+        let src = ValuePtr::new(&mut val as *mut T::Repr as VoidPtr);
+        unsafe { self.type_.assign(ValuePtr::new(self.ptr), src) };
+        true
+    }
+
+    #[inline]
+    fn as_stack_arg(&self) -> StackArg<'_> {
+        StackArg::from_raw_parts(self.type_, self.ptr)
+    }
+}
+
+impl Drop for Buffer<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            self.type_.destruct(ValuePtr::new(self.ptr));
+            alloc::dealloc(self.ptr.cast(), self.layout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{observers_at, register, CallObserver, ObservedSet, OBSERVED_NAMES, OBSERVERS};
+    use crate::types::CName;
+
+    struct Noop;
+
+    impl CallObserver for Noop {}
+
+    #[test]
+    fn observed_set() {
+        let set = ObservedSet::new();
+        assert!(!set.contains(1));
+        set.insert(1);
+        set.insert(1);
+        assert!(set.contains(1));
+        set.remove(1);
+        assert!(set.contains(1));
+        set.remove(1);
+        assert!(!set.contains(1));
+    }
+
+    #[test]
+    fn register_and_drop() {
+        let key = 0x1000;
+        let names = [CName::from(0x5EED_0001), CName::from(0x5EED_0002)];
+
+        let first = register(key, names, Arc::new(Noop));
+        let second = register(key, names, Arc::new(Noop));
+        assert_eq!(observers_at(key).map(|obs| obs.len()), Some(2));
+        assert!(OBSERVED_NAMES.contains(names[0].into()));
+
+        drop(first);
+        assert_eq!(observers_at(key).map(|obs| obs.len()), Some(1));
+        assert!(OBSERVED_NAMES.contains(names[1].into()));
+
+        drop(second);
+        assert!(observers_at(key).is_none());
+    }
+
+    #[test]
+    fn poisoned_lock() {
+        let key = 0x2000;
+        let guard = register(key, [CName::from(0x5EED_0003); 2], Arc::new(Noop));
+        thread::spawn(|| {
+            let _lock = OBSERVERS.write().unwrap();
+            panic!("poisoning the lock");
+        })
+        .join()
+        .unwrap_err();
+
+        assert_eq!(observers_at(key).map(|obs| obs.len()), Some(1));
+        drop(guard);
+        assert!(observers_at(key).is_none());
+    }
+}
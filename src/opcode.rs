@@ -0,0 +1,431 @@
+use std::any::{Any, TypeId};
+use std::mem;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::hook::HookError;
+use crate::raw::root::RED4ext as red;
+use crate::types::{IScriptable, OpcodeHandler, StackFrame};
+use crate::VoidPtr;
+
+// all handlers installed by the plugin, handlers installed for the same opcode form a chain
+// where each handler knows the one that was in place before it, removed detours are kept as
+// tombstones that only forward to the next handler, because callers may still reach their
+// trampolines through a pointer loaded before the removal
+static SLOTS: RwLock<Vec<Arc<OpcodeSlot>>> = RwLock::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+struct OpcodeSlot {
+    id: u64,
+    opcode: u8,
+    handler: usize,
+    next: Next,
+    removed: AtomicBool,
+    linked: AtomicBool,
+    detour: Option<(TypeId, Box<dyn Any + Send + Sync>)>,
+}
+
+impl OpcodeSlot {
+    #[inline]
+    fn key(&self) -> Option<TypeId> {
+        self.detour.as_ref().map(|(key, _)| *key)
+    }
+}
+
+// the handler that was in place before a slot was installed, handlers that find it on their own
+// keep it in a static instead of the slot
+enum Next {
+    Owned(AtomicUsize),
+    Shared(&'static AtomicUsize),
+}
+
+impl Deref for Next {
+    type Target = AtomicUsize;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Owned(next) => next,
+            Self::Shared(next) => next,
+        }
+    }
+}
+
+/// A handle to an installed opcode handler. The previous handler is restored when the guard is
+/// dropped.
+///
+/// A removed detour keeps forwarding calls that were already on their way to it, so its type
+/// can only be installed again on the same opcode.
+#[derive(Debug)]
+#[must_use = "the opcode handler is removed when the guard is dropped"]
+pub struct OpcodeGuard {
+    id: u64,
+}
+
+impl OpcodeGuard {
+    /// Removes the handler explicitly. Returns `true` if the handler was unlinked from the chain.
+    ///
+    /// A handler that has been wrapped by a handler installed outside of the plugin cannot be
+    /// unlinked, because the other handler keeps a pointer to it. A detour is then left in place
+    /// and only forwards to the next handler, while a replaced handler stays in place as it is.
+    /// In both cases `false` is returned.
+    #[inline]
+    pub fn remove(self) -> bool {
+        let this = mem::ManuallyDrop::new(self);
+        remove(table(), this.id)
+    }
+}
+
+impl Drop for OpcodeGuard {
+    #[inline]
+    fn drop(&mut self) {
+        remove(table(), self.id);
+    }
+}
+
+pub(crate) fn replace(opcode: u8, handler: OpcodeHandler) -> Result<OpcodeGuard, HookError> {
+    install(
+        table(),
+        opcode,
+        handler as usize,
+        Next::Owned(AtomicUsize::default()),
+        None,
+    )
+}
+
+/// Installs a handler that calls the handler stored in `next` to continue the chain. Unlike
+/// [`wrap`], the handler doesn't need to look up its slot, `next` is kept up to date for as long
+/// as the handler is installed.
+pub(crate) fn chain(
+    opcode: u8,
+    handler: OpcodeHandler,
+    next: &'static AtomicUsize,
+) -> Result<OpcodeGuard, HookError> {
+    install(table(), opcode, handler as usize, Next::Shared(next), None)
+}
+
+pub(crate) fn wrap<D>(opcode: u8, detour: D) -> Result<OpcodeGuard, HookError>
+where
+    D: Fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, VoidPtr, OpcodeHandler)
+        + Send
+        + Sync
+        + 'static,
+{
+    wrap_in(table(), opcode, detour)
+}
+
+fn wrap_in<D>(table: &[AtomicUsize; 256], opcode: u8, detour: D) -> Result<OpcodeGuard, HookError>
+where
+    D: Fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, VoidPtr, OpcodeHandler)
+        + Send
+        + Sync
+        + 'static,
+{
+    let handler = trampoline::<D> as OpcodeHandler;
+    install(
+        table,
+        opcode,
+        handler as usize,
+        Next::Owned(AtomicUsize::default()),
+        Some((TypeId::of::<D>(), Box::new(detour))),
+    )
+}
+
+/// Removes all handlers installed by the plugin.
+pub(crate) fn restore_all() {
+    let table = table();
+    let mut slots = SLOTS.write().unwrap_or_else(PoisonError::into_inner);
+    // the most recent handlers are removed first, so that the chain can be unwound in order
+    for index in (0..slots.len()).rev() {
+        remove_at(table, &mut slots, index);
+    }
+}
+
+fn install(
+    table: &[AtomicUsize; 256],
+    opcode: u8,
+    handler: usize,
+    next: Next,
+    detour: Option<(TypeId, Box<dyn Any + Send + Sync>)>,
+) -> Result<OpcodeGuard, HookError> {
+    let mut slots = SLOTS.write().unwrap_or_else(PoisonError::into_inner);
+    if let Some((key, _)) = &detour {
+        if let Some(index) = slots.iter().position(|slot| slot.key() == Some(*key)) {
+            let slot = &slots[index];
+            // a tombstone can only make way for the same detour on the same opcode, callers that
+            // still reach it will find the new slot and continue down the same chain
+            if !slot.removed.load(Ordering::Acquire)
+                || slot.linked.load(Ordering::Acquire)
+                || slot.opcode != opcode
+            {
+                return Err(HookError::DetourInUse);
+            }
+            slots.remove(index);
+        }
+    }
+    if slots.iter().any(|slot| {
+        slot.opcode == opcode && slot.handler == handler && slot.linked.load(Ordering::Acquire)
+    }) {
+        return Err(HookError::AlreadyHooked);
+    }
+
+    let entry = &table[opcode as usize];
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    // the write lock is held until the entry is swapped, so trampolines invoked in the meantime
+    // will wait for the slot
+    next.store(entry.load(Ordering::Acquire), Ordering::Release);
+    slots.push(Arc::new(OpcodeSlot {
+        id,
+        opcode,
+        handler,
+        next,
+        removed: AtomicBool::new(false),
+        linked: AtomicBool::new(true),
+        detour,
+    }));
+    entry.store(handler, Ordering::Release);
+
+    Ok(OpcodeGuard { id })
+}
+
+fn remove(table: &[AtomicUsize; 256], id: u64) -> bool {
+    let mut slots = SLOTS.write().unwrap_or_else(PoisonError::into_inner);
+    match slots.iter().position(|slot| slot.id == id) {
+        Some(index) => remove_at(table, &mut slots, index),
+        None => false,
+    }
+}
+
+fn remove_at(table: &[AtomicUsize; 256], slots: &mut Vec<Arc<OpcodeSlot>>, index: usize) -> bool {
+    let slot = &slots[index];
+    if slot.removed.swap(true, Ordering::AcqRel) {
+        return false;
+    }
+    if !unlink(table, slots, slot) {
+        // the handler on top of this one can still call it
+        return false;
+    }
+    slot.linked.store(false, Ordering::Release);
+    // trampolines need their slot even after they're unlinked, since a caller may have loaded a
+    // pointer to them before
+    if slot.detour.is_none() {
+        slots.remove(index);
+    }
+    true
+}
+
+fn unlink(table: &[AtomicUsize; 256], slots: &[Arc<OpcodeSlot>], slot: &OpcodeSlot) -> bool {
+    let next = slot.next.load(Ordering::Acquire);
+    let entry = &table[slot.opcode as usize];
+    if entry
+        .compare_exchange(slot.handler, next, Ordering::AcqRel, Ordering::Acquire)
+        .is_ok()
+    {
+        return true;
+    }
+    // another handler has been installed on top of this one, it needs to skip over it now
+    let prev = slots.iter().find(|prev| {
+        prev.opcode == slot.opcode
+            && prev.linked.load(Ordering::Acquire)
+            && prev.next.load(Ordering::Acquire) == slot.handler
+    });
+    match prev {
+        Some(prev) => {
+            prev.next.store(next, Ordering::Release);
+            true
+        }
+        None => false,
+    }
+}
+
+#[inline]
+fn table() -> &'static [AtomicUsize; 256] {
+    unsafe { &*red::OpcodeHandlers::Get().cast::<[AtomicUsize; 256]>() }
+}
+
+unsafe extern "C" fn trampoline<D>(
+    ctx: Option<&IScriptable>,
+    frame: &mut StackFrame,
+    out: VoidPtr,
+    a4: VoidPtr,
+) where
+    D: Fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, VoidPtr, OpcodeHandler)
+        + Send
+        + Sync
+        + 'static,
+{
+    // the lock is released before calling the detour, so that it can execute scripts
+    let slot = SLOTS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|slot| slot.key() == Some(TypeId::of::<D>()))
+        .cloned();
+    // slots of trampolines are never dropped, only replaced by a slot of the same detour on the
+    // same opcode, so once a trampoline is installed its slot can always be found
+    let Some(slot) = slot else {
+        return;
+    };
+    let next = unsafe { mem::transmute::<usize, OpcodeHandler>(slot.next.load(Ordering::Acquire)) };
+    let detour = slot
+        .detour
+        .as_ref()
+        .filter(|_| !slot.removed.load(Ordering::Acquire))
+        .and_then(|(_, detour)| detour.downcast_ref::<D>());
+    match detour {
+        Some(detour) => detour(ctx, frame, out, a4, next),
+        None => next(ctx, frame, out, a4),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::{mem, ptr};
+
+    use super::{remove, wrap_in, OpcodeGuard};
+    use crate::hook::HookError;
+    use crate::types::{IScriptable, OpcodeHandler, StackFrame};
+    use crate::VoidPtr;
+
+    static TABLE: [AtomicUsize; 256] = [const { AtomicUsize::new(0) }; 256];
+
+    unsafe extern "C" fn original(
+        _ctx: Option<&IScriptable>,
+        _frame: &mut StackFrame,
+        out: VoidPtr,
+        _a4: VoidPtr,
+    ) {
+        unsafe { *out.cast::<i32>() += 1 };
+    }
+
+    fn detour(
+        ctx: Option<&IScriptable>,
+        frame: &mut StackFrame,
+        out: VoidPtr,
+        a4: VoidPtr,
+        next: OpcodeHandler,
+    ) {
+        unsafe { next(ctx, frame, out, a4) };
+        unsafe { *out.cast::<i32>() += 10 };
+    }
+
+    fn call(handler: usize) -> i32 {
+        let handler = unsafe { mem::transmute::<usize, OpcodeHandler>(handler) };
+        let mut frame = unsafe { mem::zeroed::<StackFrame>() };
+        let mut out = 0;
+        unsafe {
+            handler(
+                None,
+                &mut frame,
+                &mut out as *mut i32 as VoidPtr,
+                ptr::null_mut(),
+            )
+        };
+        out
+    }
+
+    // guards remove their handlers from the game table, the tests remove them from their own
+    fn remove_from_test_table(guard: OpcodeGuard) -> bool {
+        let guard = mem::ManuallyDrop::new(guard);
+        remove(&TABLE, guard.id)
+    }
+
+    #[test]
+    fn remove_while_in_flight() {
+        let entry = &TABLE[1];
+        entry.store(original as OpcodeHandler as usize, Ordering::Release);
+        let guard = wrap_in(&TABLE, 1, |ctx, frame: &mut _, out, a4, next| {
+            detour(ctx, frame, out, a4, next)
+        })
+        .unwrap();
+        assert_eq!(call(entry.load(Ordering::Acquire)), 11);
+
+        // a caller that loaded the handler before it was removed
+        let stale = entry.load(Ordering::Acquire);
+        assert!(remove_from_test_table(guard));
+        assert_eq!(
+            entry.load(Ordering::Acquire),
+            original as OpcodeHandler as usize
+        );
+        assert_eq!(call(stale), 1);
+    }
+
+    #[test]
+    fn remove_below_another_handler() {
+        let entry = &TABLE[2];
+        entry.store(original as OpcodeHandler as usize, Ordering::Release);
+        let lower = wrap_in(&TABLE, 2, |ctx, frame: &mut _, out, a4, next| {
+            detour(ctx, frame, out, a4, next)
+        })
+        .unwrap();
+        let stale = entry.load(Ordering::Acquire);
+        let upper = wrap_in(&TABLE, 2, |ctx, frame: &mut _, out, a4, next| {
+            detour(ctx, frame, out, a4, next)
+        })
+        .unwrap();
+        assert_eq!(call(entry.load(Ordering::Acquire)), 21);
+
+        assert!(remove_from_test_table(lower));
+        assert_eq!(call(entry.load(Ordering::Acquire)), 11);
+        assert_eq!(call(stale), 1);
+
+        assert!(remove_from_test_table(upper));
+        assert_eq!(call(entry.load(Ordering::Acquire)), 1);
+    }
+
+    #[test]
+    fn remove_below_foreign_handler() {
+        static WRAPPED: AtomicUsize = AtomicUsize::new(0);
+
+        unsafe extern "C" fn foreign(
+            ctx: Option<&IScriptable>,
+            frame: &mut StackFrame,
+            out: VoidPtr,
+            a4: VoidPtr,
+        ) {
+            let next = WRAPPED.load(Ordering::Acquire);
+            unsafe { mem::transmute::<usize, OpcodeHandler>(next)(ctx, frame, out, a4) };
+            unsafe { *out.cast::<i32>() += 100 };
+        }
+
+        let entry = &TABLE[3];
+        entry.store(original as OpcodeHandler as usize, Ordering::Release);
+        let guard = wrap_in(&TABLE, 3, |ctx, frame: &mut _, out, a4, next| {
+            detour(ctx, frame, out, a4, next)
+        })
+        .unwrap();
+        WRAPPED.store(entry.load(Ordering::Acquire), Ordering::Release);
+        entry.store(foreign as OpcodeHandler as usize, Ordering::Release);
+        assert_eq!(call(entry.load(Ordering::Acquire)), 111);
+
+        // the detour stays in the chain, but only forwards to the next handler
+        assert!(!remove_from_test_table(guard));
+        assert_eq!(call(entry.load(Ordering::Acquire)), 101);
+    }
+
+    #[test]
+    fn reinstall_removed_detour() {
+        fn install(opcode: u8) -> Result<OpcodeGuard, HookError> {
+            wrap_in(&TABLE, opcode, |ctx, frame: &mut _, out, a4, next| {
+                detour(ctx, frame, out, a4, next)
+            })
+        }
+
+        let entry = &TABLE[4];
+        entry.store(original as OpcodeHandler as usize, Ordering::Release);
+        let guard = install(4).unwrap();
+        assert!(matches!(install(4), Err(HookError::DetourInUse)));
+        let stale = entry.load(Ordering::Acquire);
+        assert!(remove_from_test_table(guard));
+
+        // the tombstone keeps the detour from moving to another opcode
+        assert!(matches!(install(5), Err(HookError::DetourInUse)));
+        let guard = install(4).unwrap();
+        assert_eq!(call(entry.load(Ordering::Acquire)), 11);
+        assert_eq!(call(stale), 11);
+        assert!(remove_from_test_table(guard));
+        assert_eq!(call(stale), 1);
+    }
+}
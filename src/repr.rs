@@ -1,8 +1,9 @@
 use const_combine::bounded::const_combine as combine;
+use sealed::sealed;
 
 use crate::types::{
-    CName, EntityId, GameTime, ItemId, RedArray, RedString, Ref, ScriptClass, ScriptRef, TweakDbId,
-    Variant, WeakRef,
+    CName, DateTime, EntityId, GameTime, Guid, ItemId, LocalizationString, NodeRef, RedArray,
+    RedString, Ref, ResourcePath, ScriptClass, ScriptRef, StackFrame, TweakDbId, Variant, WeakRef,
 };
 
 /// A trait for types that can be passed across the FFI boundary to the game engine without
@@ -67,9 +68,14 @@ impl_native_repr!(bool, "Bool");
 impl_native_repr!(CName, "CName");
 impl_native_repr!(TweakDbId, "TweakDBID");
 impl_native_repr!(ItemId, "ItemID", "gameItemID");
+impl_native_repr!(LocalizationString, "LocalizationString");
 impl_native_repr!(EntityId, "EntityID", "entEntityID");
 impl_native_repr!(GameTime, "GameTime", "GameTime");
 impl_native_repr!(Variant, "Variant", "Variant");
+impl_native_repr!(Guid, "CGUID");
+impl_native_repr!(DateTime, "CDateTime");
+impl_native_repr!(NodeRef, "NodeRef");
+impl_native_repr!(ResourcePath, "redResourceReferenceScriptToken");
 
 /// A trait for types that can be converted into a representation that can be passed across
 /// the FFI boundary to the game.
@@ -143,3 +149,165 @@ where
         repr.into_iter().map(FromRepr::from_repr).collect()
     }
 }
+
+/// A trait for types that can be accepted as arguments by exported functions. Besides all
+/// [`FromRepr`] types, it's implemented for references that borrow the argument directly from the
+/// caller without copying it: `&RedArray<T>`, `&[T]`, `&RedString` and `&str`.
+///
+/// # Example
+/// ```rust
+/// use red4rs::{global, GlobalMetadata};
+///
+/// fn my_global() -> GlobalMetadata {
+///     global!(c"CountMatches", count_matches)
+/// }
+///
+/// // neither the array nor the string are copied
+/// fn count_matches(items: &[i32], name: &str) -> i32 {
+///     items
+///         .iter()
+///         .filter(|&&item| item == name.len() as i32)
+///         .count() as i32
+/// }
+/// ```
+#[sealed]
+pub trait FromArg: Sized {
+    type Repr: NativeRepr;
+    /// This type with its lifetime replaced by `'a`.
+    type Rebind<'a>;
+
+    #[doc(hidden)]
+    unsafe fn from_frame<'a>(
+        frame: &mut StackFrame,
+        storage: &'a mut Option<Self::Repr>,
+    ) -> Self::Rebind<'a>;
+}
+
+#[sealed]
+impl<A> FromArg for A
+where
+    A: FromRepr,
+    A::Repr: Default,
+{
+    type Rebind<'a> = A;
+    type Repr = A::Repr;
+
+    #[inline]
+    unsafe fn from_frame<'a>(
+        frame: &mut StackFrame,
+        _storage: &'a mut Option<Self::Repr>,
+    ) -> Self::Rebind<'a> {
+        A::from_repr(unsafe { frame.get_arg() })
+    }
+}
+
+#[sealed]
+impl<A: NativeRepr + 'static> FromArg for &RedArray<A> {
+    type Rebind<'a> = &'a RedArray<A>;
+    type Repr = RedArray<A>;
+
+    #[inline]
+    unsafe fn from_frame<'a>(
+        frame: &mut StackFrame,
+        storage: &'a mut Option<Self::Repr>,
+    ) -> Self::Rebind<'a> {
+        unsafe { frame.borrow_arg(storage) }
+    }
+}
+
+#[sealed]
+impl<A: NativeRepr + 'static> FromArg for &[A] {
+    type Rebind<'a> = &'a [A];
+    type Repr = RedArray<A>;
+
+    #[inline]
+    unsafe fn from_frame<'a>(
+        frame: &mut StackFrame,
+        storage: &'a mut Option<Self::Repr>,
+    ) -> Self::Rebind<'a> {
+        unsafe { frame.borrow_arg::<RedArray<A>>(storage) }
+    }
+}
+
+#[sealed]
+impl FromArg for &RedString {
+    type Rebind<'a> = &'a RedString;
+    type Repr = RedString;
+
+    #[inline]
+    unsafe fn from_frame<'a>(
+        frame: &mut StackFrame,
+        storage: &'a mut Option<Self::Repr>,
+    ) -> Self::Rebind<'a> {
+        unsafe { frame.borrow_arg(storage) }
+    }
+}
+
+/// Strings that are not valid UTF-8 are converted lossily, with invalid sequences replaced by
+/// `U+FFFD`. Use [`&RedString`](RedString) to receive the original bytes.
+#[sealed]
+impl FromArg for &str {
+    type Rebind<'a> = &'a str;
+    type Repr = RedString;
+
+    #[inline]
+    unsafe fn from_frame<'a>(
+        frame: &mut StackFrame,
+        storage: &'a mut Option<Self::Repr>,
+    ) -> Self::Rebind<'a> {
+        let arg: *const RedString = unsafe { frame.borrow_arg::<RedString>(&mut *storage) };
+        unsafe { str_from_arg(arg, storage) }
+    }
+}
+
+// the string is borrowed either from the frame or from the storage, the storage is only
+// overwritten after the string is no longer used
+unsafe fn str_from_arg<'a>(arg: *const RedString, storage: &'a mut Option<RedString>) -> &'a str {
+    match unsafe { &*arg }.as_str() {
+        Ok(str) => str,
+        Err(_) => {
+            let lossy = RedString::from(unsafe { &*arg }.as_str_lossy().into_owned());
+            storage.insert(lossy).as_str().unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{str_from_arg, FromArg, NativeRepr};
+    use crate::types::{RedArray, RedString};
+
+    #[test]
+    fn borrowed_arg_types() {
+        assert_eq!(<&RedArray<f32> as FromArg>::Repr::NAME, "array:Float");
+        assert_eq!(<&[i32] as FromArg>::Repr::NAME, "array:Int32");
+        assert_eq!(<&RedString as FromArg>::Repr::NAME, "String");
+        assert_eq!(<&str as FromArg>::Repr::NAME, "String");
+    }
+
+    #[test]
+    fn str_borrowed_from_variable() {
+        let arg = RedString::from("abc");
+        let mut storage = None;
+        let str = unsafe { str_from_arg(&arg, &mut storage) };
+        assert_eq!(str, "abc");
+        assert!(storage.is_none());
+    }
+
+    #[test]
+    fn str_with_invalid_utf8() {
+        let arg = RedString::from(&b"a\xffb"[..]);
+        let mut storage = None;
+        let str = unsafe { str_from_arg(&arg, &mut storage) };
+        assert_eq!(str, "a\u{FFFD}b");
+        assert_eq!(arg.as_bytes(), b"a\xffb");
+    }
+
+    #[test]
+    fn str_with_invalid_utf8_in_storage() {
+        let mut storage = Some(RedString::from(&b"\xff"[..]));
+        let arg: *const RedString = storage.as_ref().unwrap();
+        let str = unsafe { str_from_arg(arg, &mut storage) };
+        assert_eq!(str, "\u{FFFD}");
+    }
+}
@@ -1,2 +1,4 @@
 mod rtti;
 pub use rtti::{RttiRegistrator, RttiSystem, RttiSystemMut};
+mod tweak_db;
+pub use tweak_db::{TweakDb, TweakDbError};
@@ -0,0 +1,470 @@
+use std::cell::UnsafeCell;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::{iter, ptr};
+
+use thiserror::Error;
+
+use crate::raw::root::RED4ext as red;
+use crate::repr::NativeRepr;
+use crate::types::{CName, Class, IScriptable, RedArray, Ref, TweakDbId, Type};
+use crate::{RttiSystem, VoidPtr};
+
+// the seed used by the game to hash the names of record types
+const RECORD_TYPE_SEED: u32 = 0x5EED_BA5E;
+
+// the database only locks its flats and records for the duration of a single call, operations
+// that check the database before changing it hold this lock to not race with each other
+static EDIT_LOCK: Mutex<()> = Mutex::new(());
+
+/// The TweakDB, a database of records and flat values that describe items, characters, vehicles
+/// and most of the other game data.
+///
+/// The flats and the records are guarded by separate locks inside the database. Every operation
+/// acquires the locks it needs only for its own duration and returns owned values, so no lock
+/// is held between calls. Operations that check the database before changing it, like
+/// [`TweakDb::set_flat`] and [`TweakDb::create_record`], are additionally serialized with each
+/// other, but not with changes made by the game itself.
+///
+/// # Example
+/// ```rust
+/// use red4rs::types::TweakDbId;
+/// use red4rs::{TweakDb, TweakDbError};
+///
+/// fn double_mass() -> Result<(), TweakDbError> {
+///     let db = TweakDb::get();
+///     let record = TweakDbId::new("Vehicle.v_sport1_quadra_turbo_player");
+///     let flat = TweakDbId::new_from_base(record, ".mass");
+///     let mass: f32 = db.get_flat(flat).ok_or(TweakDbError::FlatNotFound(flat))?;
+///     db.set_flat(flat, mass * 2.0)?;
+///     db.update_record(record)
+/// }
+/// ```
+#[repr(transparent)]
+pub struct TweakDb(UnsafeCell<red::TweakDB>);
+
+impl TweakDb {
+    /// Retrieve the TweakDB instance.
+    #[inline]
+    pub fn get() -> &'static Self {
+        unsafe { &*(red::TweakDB::Get() as *const Self) }
+    }
+
+    /// Retrieve a copy of a flat value. Returns [`None`] if the flat does not exist or if it's
+    /// of a different type than `T`.
+    pub fn get_flat<T: NativeRepr + Clone>(&self, id: TweakDbId) -> Option<T> {
+        let flat = unsafe { red::TweakDB_GetFlatValue(self.as_ptr(), id.to_inner()) };
+        if flat.is_null() {
+            return None;
+        }
+        let value = unsafe { red::TweakDB_FlatValue_GetValue(flat) };
+        let type_ = unsafe { value.type_.cast::<Type>().as_ref() }?;
+        if type_.name() != CName::new(T::NAME) {
+            return None;
+        }
+        Some(unsafe { &*value.value.cast::<T>() }.clone())
+    }
+
+    /// Returns `true` if a flat with the given id exists.
+    #[inline]
+    pub fn has_flat(&self, id: TweakDbId) -> bool {
+        !unsafe { red::TweakDB_GetFlatValue(self.as_ptr(), id.to_inner()) }.is_null()
+    }
+
+    /// Set a flat value, creating the flat if it doesn't exist. The records that use the flat
+    /// need to be updated with [`TweakDb::update_record`] for the change to become visible.
+    pub fn set_flat<T: NativeRepr>(&self, id: TweakDbId, mut value: T) -> Result<(), TweakDbError> {
+        let type_ = RttiSystem::get()
+            .get_type(CName::new(T::NAME))
+            .map(|ty| ty.as_raw() as *const _ as *mut red::CBaseRTTIType)
+            .ok_or(TweakDbError::UnknownType(T::NAME))?;
+        let stack = red::CStackType {
+            type_,
+            value: &mut value as *mut T as VoidPtr,
+        };
+        let _lock = edit_lock();
+        // the value is copied into the flat buffer, the original is dropped normally
+        replace_flat(self, id, &stack)
+    }
+
+    /// Retrieve a record by its id.
+    pub fn get_record(&self, id: TweakDbId) -> Option<Ref<IScriptable>> {
+        let mut record = Ref::<IScriptable>::default();
+        let found = unsafe {
+            red::TweakDB_TryGetRecord(
+                self.as_ptr(),
+                id.to_inner(),
+                &mut record as *mut _ as *mut red::Handle<red::IScriptable>,
+            )
+        };
+        found.then_some(record)
+    }
+
+    /// Returns `true` if a record with the given id exists.
+    #[inline]
+    pub fn has_record(&self, id: TweakDbId) -> bool {
+        self.get_record(id).is_some()
+    }
+
+    /// Retrieve the class of a record, e.g. `gamedataItem_Record`.
+    pub fn record_class(&self, id: TweakDbId) -> Option<&'static Class> {
+        let record = self.get_record(id)?;
+        let class = unsafe { record.instance() }?.class();
+        // classes are owned by the RTTI system and are never freed
+        Some(unsafe { &*(class as *const Class) })
+    }
+
+    /// Retrieve all records of a class, including the records of its subclasses.
+    pub fn records_of(&self, class: &Class) -> RedArray<Ref<IScriptable>> {
+        let mut out = RedArray::default();
+        unsafe {
+            red::TweakDB_TryGetRecordsByType(
+                self.as_ptr(),
+                class as *const Class as *const red::CBaseRTTIType,
+                &mut out as *mut _ as *mut red::DynArray<red::Handle<red::IScriptable>>,
+            )
+        };
+        out
+    }
+
+    /// Create a new empty record of a given class. The class should be a record class, e.g.
+    /// `gamedataItem_Record`.
+    pub fn create_record(&self, id: TweakDbId, class: &Class) -> Result<(), TweakDbError> {
+        let _lock = edit_lock();
+        self.create_record_locked(id, class)
+    }
+
+    fn create_record_locked(&self, id: TweakDbId, class: &Class) -> Result<(), TweakDbError> {
+        if self.has_record(id) {
+            return Err(TweakDbError::RecordExists(id));
+        }
+        let hash = record_type_hash(class.name().as_str());
+        unsafe { red::TweakDB_CreateRecord(self.as_ptr(), id.to_inner(), hash) };
+        if self.has_record(id) {
+            Ok(())
+        } else {
+            Err(TweakDbError::OperationFailed)
+        }
+    }
+
+    /// Create a new record with the same class and flat values as an existing one. The flats
+    /// are copied for each property of the record class.
+    pub fn clone_record(&self, id: TweakDbId, source: TweakDbId) -> Result<(), TweakDbError> {
+        let class = self
+            .record_class(source)
+            .ok_or(TweakDbError::RecordNotFound(source))?;
+        let lock = edit_lock();
+        self.create_record_locked(id, class)?;
+
+        let props = iter::once(class)
+            .chain(class.base_iter())
+            .flat_map(|class| class.properties().iter())
+            .map(|prop| prop.name().as_str());
+        copy_flats(self, source, id, props)?;
+        drop(lock);
+        self.update_record(id)
+    }
+
+    /// Update a record with the current values of its flats.
+    pub fn update_record(&self, id: TweakDbId) -> Result<(), TweakDbError> {
+        let record = self
+            .get_record(id)
+            .ok_or(TweakDbError::RecordNotFound(id))?;
+        let updated = unsafe {
+            let update = crate::fn_from_hash!(
+                TweakDB_UpdateRecord,
+                unsafe extern "C" fn(*mut red::TweakDB, VoidPtr) -> bool
+            );
+            let instance = record
+                .instance()
+                .map_or(ptr::null_mut(), |inst| inst as *const _ as VoidPtr);
+            update(self.as_ptr(), instance)
+        };
+        if updated {
+            Ok(())
+        } else {
+            Err(TweakDbError::OperationFailed)
+        }
+    }
+
+    // the database functions take their own locks, so they're called through a raw pointer
+    // without ever creating a mutable reference
+    #[inline]
+    fn as_ptr(&self) -> *mut red::TweakDB {
+        self.0.get()
+    }
+}
+
+// the flat operations of the database, the editing logic is written against this trait so that
+// it can be tested without the game
+trait FlatStore {
+    type Value;
+
+    fn flat_value(&self, id: TweakDbId) -> Option<Self::Value>;
+    // returns the offset of the copied value in the flat buffer, or a negative number on failure
+    fn create_flat_value(&self, value: &Self::Value) -> i32;
+    fn remove_flat(&self, id: TweakDbId);
+    // the id carries the offset of the value, fails if the flat already exists
+    fn add_flat(&self, id: TweakDbId) -> bool;
+}
+
+impl FlatStore for TweakDb {
+    type Value = red::CStackType;
+
+    fn flat_value(&self, id: TweakDbId) -> Option<Self::Value> {
+        let flat = unsafe { red::TweakDB_GetFlatValue(self.as_ptr(), id.to_inner()) };
+        (!flat.is_null()).then(|| unsafe { red::TweakDB_FlatValue_GetValue(flat) })
+    }
+
+    #[inline]
+    fn create_flat_value(&self, value: &Self::Value) -> i32 {
+        unsafe { red::TweakDB_CreateFlatValue(self.as_ptr(), value) }
+    }
+
+    #[inline]
+    fn remove_flat(&self, id: TweakDbId) {
+        unsafe { red::TweakDB_RemoveFlat(self.as_ptr(), id.to_inner()) };
+    }
+
+    #[inline]
+    fn add_flat(&self, id: TweakDbId) -> bool {
+        unsafe { red::TweakDB_AddFlat(self.as_ptr(), id.to_inner()) }
+    }
+}
+
+fn replace_flat<S: FlatStore>(
+    store: &S,
+    id: TweakDbId,
+    value: &S::Value,
+) -> Result<(), TweakDbError> {
+    let offset = store.create_flat_value(value);
+    if offset < 0 {
+        return Err(TweakDbError::OperationFailed);
+    }
+    if store.flat_value(id).is_some() {
+        store.remove_flat(id);
+    }
+    if store.add_flat(id.with_tdb_offset(offset)) {
+        Ok(())
+    } else {
+        Err(TweakDbError::OperationFailed)
+    }
+}
+
+// copies the flats of the given properties, the properties without a flat in the source record
+// are skipped
+fn copy_flats<'a, S: FlatStore>(
+    store: &S,
+    source: TweakDbId,
+    target: TweakDbId,
+    props: impl IntoIterator<Item = &'a str>,
+) -> Result<(), TweakDbError> {
+    for prop in props {
+        let name = format!(".{prop}");
+        if let Some(value) = store.flat_value(TweakDbId::new_from_base(source, &name)) {
+            replace_flat(store, TweakDbId::new_from_base(target, &name), &value)?;
+        }
+    }
+    Ok(())
+}
+
+#[inline]
+fn edit_lock() -> MutexGuard<'static, ()> {
+    EDIT_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(Debug, Error)]
+pub enum TweakDbError {
+    #[error("record {0:?} does not exist")]
+    RecordNotFound(TweakDbId),
+    #[error("record {0:?} already exists")]
+    RecordExists(TweakDbId),
+    #[error("flat {0:?} does not exist")]
+    FlatNotFound(TweakDbId),
+    #[error("type {0} does not exist")]
+    UnknownType(&'static str),
+    #[error("TweakDB operation failed")]
+    OperationFailed,
+}
+
+// record types are identified by the name of their class without the `gamedata` prefix and the
+// `_Record` suffix, e.g. `gamedataItem_Record` becomes `Item`
+fn record_type_hash(class_name: &str) -> u32 {
+    let name = class_name.strip_prefix("gamedata").unwrap_or(class_name);
+    let name = name.strip_suffix("_Record").unwrap_or(name);
+    murmur3_32(name.as_bytes(), RECORD_TYPE_SEED)
+}
+
+const fn murmur3_32(bytes: &[u8], seed: u32) -> u32 {
+    const fn scramble(k: u32) -> u32 {
+        k.wrapping_mul(0xCC9E_2D51)
+            .rotate_left(15)
+            .wrapping_mul(0x1B87_3593)
+    }
+
+    let mut hash = seed;
+    let mut i = 0;
+    while i + 4 <= bytes.len() {
+        let k = u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        hash ^= scramble(k);
+        hash = hash
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xE654_6B64);
+        i += 4;
+    }
+    let mut tail = 0;
+    let mut j = bytes.len();
+    while j > i {
+        j -= 1;
+        tail = (tail << 8) | bytes[j] as u32;
+    }
+    hash ^= scramble(tail);
+
+    hash ^= bytes.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85EB_CA6B);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xC2B2_AE35);
+    hash ^ (hash >> 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+
+    use super::{
+        copy_flats, murmur3_32, record_type_hash, replace_flat, FlatStore, TweakDbError,
+        RECORD_TYPE_SEED,
+    };
+    use crate::types::TweakDbId;
+
+    const RECORD: TweakDbId = TweakDbId::new("Items.Record");
+    const CLONE: TweakDbId = TweakDbId::new("Items.Clone");
+
+    #[derive(Default)]
+    struct MockFlats {
+        buffer: RefCell<Vec<i32>>,
+        flats: RefCell<HashMap<(u32, u8), i32>>,
+        fail_create: Cell<bool>,
+        fail_add: Cell<bool>,
+    }
+
+    impl MockFlats {
+        fn get(&self, id: TweakDbId) -> Option<i32> {
+            self.flat_value(id)
+        }
+
+        fn set(&self, id: TweakDbId, value: i32) {
+            replace_flat(self, id, &value).unwrap();
+        }
+    }
+
+    impl FlatStore for MockFlats {
+        type Value = i32;
+
+        fn flat_value(&self, id: TweakDbId) -> Option<Self::Value> {
+            let offset = *self.flats.borrow().get(&(id.hash(), id.len()))?;
+            Some(self.buffer.borrow()[offset as usize])
+        }
+
+        fn create_flat_value(&self, value: &Self::Value) -> i32 {
+            if self.fail_create.get() {
+                return -1;
+            }
+            let mut buffer = self.buffer.borrow_mut();
+            buffer.push(*value);
+            buffer.len() as i32 - 1
+        }
+
+        fn remove_flat(&self, id: TweakDbId) {
+            self.flats.borrow_mut().remove(&(id.hash(), id.len()));
+        }
+
+        fn add_flat(&self, id: TweakDbId) -> bool {
+            if self.fail_add.get() {
+                return false;
+            }
+            let mut flats = self.flats.borrow_mut();
+            let key = (id.hash(), id.len());
+            if flats.contains_key(&key) {
+                return false;
+            }
+            flats.insert(key, id.tdb_offset());
+            true
+        }
+    }
+
+    #[test]
+    fn replace() {
+        let flats = MockFlats::default();
+        let id = TweakDbId::new_from_base(RECORD, ".mass");
+        assert!(replace_flat(&flats, id, &1).is_ok());
+        assert_eq!(flats.get(id), Some(1));
+        assert!(replace_flat(&flats, id, &2).is_ok());
+        assert_eq!(flats.get(id), Some(2));
+
+        flats.fail_create.set(true);
+        assert!(matches!(
+            replace_flat(&flats, id, &3),
+            Err(TweakDbError::OperationFailed)
+        ));
+        assert_eq!(flats.get(id), Some(2));
+
+        flats.fail_create.set(false);
+        flats.fail_add.set(true);
+        assert!(matches!(
+            replace_flat(&flats, id, &4),
+            Err(TweakDbError::OperationFailed)
+        ));
+    }
+
+    #[test]
+    fn copy() {
+        let flats = MockFlats::default();
+        flats.set(TweakDbId::new_from_base(RECORD, ".mass"), 10);
+        flats.set(TweakDbId::new_from_base(RECORD, ".price"), 20);
+        flats.set(TweakDbId::new_from_base(CLONE, ".price"), 30);
+
+        assert!(copy_flats(&flats, RECORD, CLONE, ["mass", "price", "quality"]).is_ok());
+        assert_eq!(
+            flats.get(TweakDbId::new_from_base(CLONE, ".mass")),
+            Some(10)
+        );
+        assert_eq!(
+            flats.get(TweakDbId::new_from_base(CLONE, ".price")),
+            Some(20)
+        );
+        assert_eq!(flats.get(TweakDbId::new_from_base(CLONE, ".quality")), None);
+        assert_eq!(
+            flats.get(TweakDbId::new_from_base(RECORD, ".mass")),
+            Some(10)
+        );
+
+        flats.fail_add.set(true);
+        assert!(matches!(
+            copy_flats(&flats, RECORD, CLONE, ["mass"]),
+            Err(TweakDbError::OperationFailed)
+        ));
+    }
+
+    #[test]
+    fn murmur3() {
+        assert_eq!(murmur3_32(b"", 0), 0);
+        assert_eq!(murmur3_32(b"", 1), 0x514E_28B7);
+        assert_eq!(murmur3_32(b"hello", 0), 0x248B_FA47);
+        assert_eq!(murmur3_32(b"Hello, world!", 1234), 0xFAF6_CDB3);
+    }
+
+    #[test]
+    fn record_type() {
+        assert_eq!(
+            record_type_hash("gamedataItem_Record"),
+            murmur3_32(b"Item", RECORD_TYPE_SEED)
+        );
+        assert_eq!(
+            record_type_hash("Item"),
+            murmur3_32(b"Item", RECORD_TYPE_SEED)
+        );
+    }
+}
@@ -3,21 +3,42 @@ pub use cruid::Cruid;
 mod engine_time;
 pub use engine_time::EngineTime;
 mod entity_id;
-pub use entity_id::EntityId;
+pub use entity_id::{EntityId, EntityKind};
 mod game_time;
 pub use game_time::GameTime;
 mod item_id;
-pub use item_id::{GameEItemIdFlag, GamedataItemStructure, ItemId};
+pub use item_id::{GameEItemIdFlag, GamedataItemStructure, ItemId, ItemIdBuilder, Seed};
 mod res;
-pub use res::{RaRef, ResRef};
+pub(crate) use res::stop_pending_loads;
+pub use res::{
+    RaRef, ResRef, ResourceLoadError, ResourcePath, ResourcePathError, ResourceRef, ResourceToken,
+};
 mod tweak_db_id;
-pub use tweak_db_id::TweakDbId;
+pub use tweak_db_id::{TweakDbId, TweakDbIdParseError};
 mod array;
-pub use array::{IntoIter, RedArray};
+pub use array::{Drain, IntoIter, RedArray};
 mod refs;
 pub use refs::{ClassKind, Native, Ref, ScriptClass, ScriptClassOps, ScriptRef, Scripted, WeakRef};
 mod string;
 pub use string::RedString;
+mod loc_string;
+pub use loc_string::LocalizationString;
+mod variant;
+pub use variant::Variant;
+mod data_buffer;
+pub use data_buffer::{DataBuffer, DeferredDataBuffer, SharedDataBuffer};
+mod job;
+pub use job::JobHandle;
+mod curve;
+pub use curve::{Curve, CurvePoint, Interpolate, Interpolation, MultiChannelCurve};
+mod guid;
+pub use guid::{Guid, GuidParseError};
+mod date_time;
+pub use date_time::{DateTime, DateTimeError};
+mod node_ref;
+pub use node_ref::NodeRef;
+mod registry;
+pub use registry::HashRegistry;
 mod cname;
 pub use cname::{CName, CNamePool};
 mod rtti;
@@ -28,21 +49,27 @@ pub use rtti::{
     ScriptRefType, StaticArrayType, StaticMethod, TaggedType, Type, ValueContainer, ValuePtr,
     WeakRefType,
 };
-mod bytecode;
+/// REDscript bytecode instructions and a decoder for them.
+pub mod bytecode;
 pub use bytecode::{
-    Instr, InvokeStatic, InvokeVirtual, OpcodeHandler, CALL_INSTR_SIZE, OPCODE_SIZE,
+    DecodeError, Instr, Instruction, InvokeStatic, InvokeVirtual, Op, OpcodeHandler,
+    CALL_INSTR_SIZE, OPCODE_SIZE,
 };
 mod stack;
 pub use stack::{StackArg, StackFrame};
+mod backtrace;
+pub use backtrace::{BacktraceFrame, ScriptBacktrace};
 mod allocator;
 pub use allocator::{IAllocator, PoolRef, Poolable, PoolableOps};
 mod hash;
-pub use hash::{Hash, RedHashMap};
+pub use hash::{Entry, Hash, OccupiedEntry, RedHashMap, VacantEntry};
+mod set;
+pub use set::RedHashSet;
+mod sorted;
+pub use sorted::{RedMap, RedSet, SortedArray};
+mod ptr;
+pub use ptr::{SharedPtr, UniquePtr};
 mod sync;
 pub use sync::{RwSpinLockReadGuard, RwSpinLockWriteGuard};
 mod misc;
-pub use misc::{
-    Curve, DataBuffer, DateTime, DeferredDataBuffer, EditorObjectId, Guid, LocalizationString,
-    MessageResourcePath, MultiChannelCurve, NodeRef, ResourceRef, SharedDataBuffer, StaticArray,
-    Variant,
-};
+pub use misc::{EditorObjectId, MessageResourcePath, StaticArray};
@@ -1,4 +1,5 @@
 use std::num::NonZero;
+use std::sync::atomic::{AtomicPtr, Ordering};
 use std::{mem, ops, ptr};
 
 use once_cell::race::OnceNonZeroUsize;
@@ -9,6 +10,10 @@ use crate::raw::root::RED4ext as red;
 use crate::raw::root::RED4ext::Memory::AllocationResult;
 use crate::{fnv1a32, VoidPtr};
 
+// the allocator used by containers created on the Rust side, it's the engine allocator unless
+// another one is installed, which allows the container code to run without the game
+static CONTAINER_ALLOCATOR: AtomicPtr<IAllocator> = AtomicPtr::new(ptr::null_mut());
+
 /// An interface for allocating and freeing memory.
 #[derive(Debug)]
 #[repr(transparent)]
@@ -42,6 +47,23 @@ impl IAllocator {
         };
         result.memory.cast()
     }
+
+    /// Returns the allocator of the engine pool. The allocator is owned by the game.
+    #[inline]
+    pub fn engine() -> &'static Self {
+        unsafe { &*red::Memory::EngineAllocator::Get().cast::<Self>() }
+    }
+
+    #[inline]
+    pub(super) fn container() -> &'static Self {
+        Self::installed().unwrap_or_else(Self::engine)
+    }
+
+    // returns the allocator installed in place of the engine allocator, if any
+    #[inline]
+    pub(super) fn installed() -> Option<&'static Self> {
+        unsafe { CONTAINER_ALLOCATOR.load(Ordering::Acquire).as_ref() }
+    }
 }
 
 /// A reference to a value stored in a pool.
@@ -225,3 +247,86 @@ unsafe fn vault_get(handle: u32) -> Option<NonZero<usize>> {
 
     NonZero::new(storage as usize)
 }
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::alloc::{self, Layout};
+    use std::sync::atomic::Ordering;
+
+    use super::{IAllocator, CONTAINER_ALLOCATOR};
+    use crate::raw::root::RED4ext as red;
+    use crate::raw::root::RED4ext::Memory::AllocationResult;
+
+    // an allocator backed by the global Rust allocator, it only implements `AllocAligned` and
+    // `Free`, because these are the only methods used by the crate
+    static ALLOCATOR: MockAllocator = MockAllocator { vtable: &VTABLE };
+    static VTABLE: Vtable = Vtable {
+        alloc: unsupported,
+        alloc_aligned,
+        realloc: unsupported,
+        realloc_aligned: unsupported,
+        free,
+        sub_28: unsupported,
+        get_handle: unsupported,
+    };
+
+    #[repr(C)]
+    struct MockAllocator {
+        vtable: &'static Vtable,
+    }
+
+    #[repr(C)]
+    struct Vtable {
+        alloc: unsafe extern "C" fn(),
+        alloc_aligned:
+            unsafe extern "C" fn(*mut red::Memory::IAllocator, u32, u32) -> AllocationResult,
+        realloc: unsafe extern "C" fn(),
+        realloc_aligned: unsafe extern "C" fn(),
+        free: unsafe extern "C" fn(*mut red::Memory::IAllocator, *mut AllocationResult),
+        sub_28: unsafe extern "C" fn(),
+        get_handle: unsafe extern "C" fn(),
+    }
+
+    /// Returns an allocator that can be used in tests without the game.
+    pub(crate) fn allocator() -> &'static IAllocator {
+        unsafe { &*(&ALLOCATOR as *const MockAllocator).cast::<IAllocator>() }
+    }
+
+    /// Installs the mock allocator as the allocator of new containers.
+    pub(crate) fn install() {
+        CONTAINER_ALLOCATOR.store(allocator() as *const _ as *mut _, Ordering::Release);
+    }
+
+    unsafe extern "C" fn alloc_aligned(
+        _this: *mut red::Memory::IAllocator,
+        size: u32,
+        alignment: u32,
+    ) -> AllocationResult {
+        // the size and alignment are stored in a header preceding the allocation
+        let align = (alignment as usize).max(2 * std::mem::size_of::<usize>());
+        let layout = Layout::from_size_align(size as usize + align, align).unwrap();
+        unsafe {
+            let memory = alloc::alloc(layout).add(align).cast::<usize>();
+            memory.sub(1).write(size as usize);
+            memory.sub(2).write(align);
+            AllocationResult {
+                memory: memory.cast(),
+                size: size as _,
+            }
+        }
+    }
+
+    unsafe extern "C" fn free(_this: *mut red::Memory::IAllocator, alloc: *mut AllocationResult) {
+        unsafe {
+            let memory = (*alloc).memory.cast::<usize>();
+            let size = memory.sub(1).read();
+            let align = memory.sub(2).read();
+            let layout = Layout::from_size_align(size + align, align).unwrap();
+            alloc::dealloc(memory.cast::<u8>().sub(align), layout);
+        }
+    }
+
+    unsafe extern "C" fn unsupported() {
+        panic!("unsupported mock allocator method");
+    }
+}
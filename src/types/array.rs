@@ -76,7 +76,245 @@ impl<T> RedArray<T> {
         self.realloc(expected.max(self.capacity() + self.capacity() / 2));
     }
 
+    /// Removes the last element from the array and returns it, or `None` if it is empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        self.0.size -= 1;
+        Some(unsafe { ptr::read(self.0.entries.add(self.len() as usize)) })
+    }
+
+    /// Inserts an element at position `index`, shifting all elements after it to the right.
+    ///
+    /// # Panics
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: u32, value: T) {
+        let len = self.len();
+        assert!(
+            index <= len,
+            "insertion index {index} is out of bounds (len {len})"
+        );
+        self.reserve(1);
+        unsafe {
+            let p = self.0.entries.add(index as usize);
+            ptr::copy(p, p.add(1), (len - index) as usize);
+            ptr::write(p, value);
+        }
+        self.0.size = len + 1;
+    }
+
+    /// Removes and returns the element at position `index`, shifting all elements after it to
+    /// the left.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: u32) -> T {
+        let len = self.len();
+        assert!(
+            index < len,
+            "removal index {index} is out of bounds (len {len})"
+        );
+        unsafe {
+            let p = self.0.entries.add(index as usize);
+            let value = ptr::read(p);
+            ptr::copy(p.add(1), p, (len - index - 1) as usize);
+            self.0.size = len - 1;
+            value
+        }
+    }
+
+    /// Removes and returns the element at position `index`, replacing it with the last element
+    /// of the array. This does not preserve ordering, but is O(1).
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: u32) -> T {
+        let len = self.len();
+        assert!(
+            index < len,
+            "swap_remove index {index} is out of bounds (len {len})"
+        );
+        unsafe {
+            let p = self.0.entries.add(index as usize);
+            let value = ptr::read(p);
+            ptr::copy(self.0.entries.add(len as usize - 1), p, 1);
+            self.0.size = len - 1;
+            value
+        }
+    }
+
+    /// Shortens the array, keeping the first `len` elements and dropping the rest. Has no
+    /// effect if `len` is greater than the current length.
+    pub fn truncate(&mut self, len: u32) {
+        if len >= self.len() {
+            return;
+        }
+        let tail: *mut [T] = &mut self[len as usize..];
+        // the length is updated first, so that a panicking destructor cannot cause a double drop
+        self.0.size = len;
+        unsafe { ptr::drop_in_place(tail) }
+    }
+
+    /// Retains only the elements specified by the predicate, preserving their order.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let len = self.len() as usize;
+        // if the predicate panics, the remaining elements are leaked instead of dropped twice
+        self.0.size = 0;
+        let mut deleted = 0;
+        for i in 0..len {
+            unsafe {
+                let cur = self.0.entries.add(i);
+                if !f(&*cur) {
+                    ptr::drop_in_place(cur);
+                    deleted += 1;
+                } else if deleted > 0 {
+                    ptr::copy_nonoverlapping(cur, cur.sub(deleted), 1);
+                }
+            }
+        }
+        self.0.size = (len - deleted) as u32;
+    }
+
+    /// Removes the specified range from the array and returns the removed elements as an
+    /// iterator. Elements that are not consumed by the iterator are dropped with it.
+    ///
+    /// # Panics
+    /// Panics if the range is out of bounds or the start is greater than the end.
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, T>
+    where
+        R: ops::RangeBounds<u32>,
+    {
+        let len = self.len();
+        let start = match range.start_bound() {
+            ops::Bound::Included(&n) => n,
+            ops::Bound::Excluded(&n) => n + 1,
+            ops::Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            ops::Bound::Included(&n) => n + 1,
+            ops::Bound::Excluded(&n) => n,
+            ops::Bound::Unbounded => len,
+        };
+        assert!(
+            start <= end,
+            "drain start {start} is greater than end {end}"
+        );
+        assert!(end <= len, "drain end {end} is out of bounds (len {len})");
+
+        // the array is truncated while the drain is alive, the tail is moved back on drop
+        self.0.size = start;
+        Drain {
+            ptr: unsafe { self.0.entries.add(start as usize) },
+            end: unsafe { self.0.entries.add(end as usize) },
+            tail_start: end,
+            tail_len: len - end,
+            array: self,
+        }
+    }
+
+    /// Clones and appends all elements of a slice to the array.
+    pub fn extend_from_slice(&mut self, other: &[T])
+    where
+        T: Clone,
+    {
+        self.reserve(other.len() as u32);
+        for item in other {
+            self.push(item.clone());
+        }
+    }
+
+    /// Removes consecutive repeated elements from the array.
+    #[inline]
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b);
+    }
+
+    /// Removes consecutive elements that resolve to the same key.
+    #[inline]
+    pub fn dedup_by_key<K, F>(&mut self, mut key: F)
+    where
+        F: FnMut(&mut T) -> K,
+        K: PartialEq,
+    {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// Removes consecutive elements for which `same_bucket` returns `true`. The predicate is
+    /// passed the current element and the last element that was kept.
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        let len = self.len() as usize;
+        if len <= 1 {
+            return;
+        }
+        // same as in `retain`, a panic leaks the remaining elements
+        self.0.size = 0;
+        let mut kept = 1;
+        for i in 1..len {
+            unsafe {
+                let cur = self.0.entries.add(i);
+                let last = self.0.entries.add(kept - 1);
+                if same_bucket(&mut *cur, &mut *last) {
+                    ptr::drop_in_place(cur);
+                } else {
+                    ptr::copy(cur, self.0.entries.add(kept), 1);
+                    kept += 1;
+                }
+            }
+        }
+        self.0.size = kept as u32;
+    }
+
+    /// Shrinks the capacity of the array to match its length.
+    pub fn shrink_to_fit(&mut self) {
+        if self.capacity() == self.len() {
+            return;
+        }
+        if self.is_empty() {
+            unsafe { (*get_allocator(&self.0)).free(self.0.entries) };
+            self.0.entries = ptr::null_mut();
+            self.0.capacity = 0;
+        } else {
+            self.realloc(self.len());
+        }
+    }
+
+    /// Splits the array in two at the given index. Returns a newly allocated array containing
+    /// the elements in the range `[at, len)`, leaving the elements `[0, at)` in this one.
+    ///
+    /// # Panics
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: u32) -> Self {
+        let len = self.len();
+        assert!(at <= len, "split index {at} is out of bounds (len {len})");
+        let count = len - at;
+        let mut other = Self::with_capacity(count);
+        if count > 0 {
+            unsafe {
+                let src = self.0.entries.add(at as usize);
+                ptr::copy_nonoverlapping(src, other.0.entries, count as _);
+            }
+        }
+        self.0.size = at;
+        other.0.size = count;
+        other
+    }
+
     fn realloc(&mut self, cap: u32) {
+        if IAllocator::installed().is_some() {
+            self.realloc_without_game(cap);
+            return;
+        }
         let size = mem::size_of::<T>();
         let align = mem::align_of::<T>().max(8);
         unsafe {
@@ -87,6 +325,37 @@ impl<T> RedArray<T> {
             realloc(self as *mut _ as VoidPtr, cap, size as u32, align as u32, 0);
         };
     }
+
+    // reallocates the entries on the Rust side, it's only used when an allocator is installed
+    // in place of the engine allocator, which happens when the crate runs without the game
+    fn realloc_without_game(&mut self, cap: u32) {
+        if cap == 0 {
+            return;
+        }
+        let size = mem::size_of::<T>() * cap as usize;
+        let offset = size.next_multiple_of(mem::size_of::<usize>());
+        let align = mem::align_of::<T>().max(8);
+        unsafe {
+            let allocator = get_allocator(&self.0);
+            // the allocator itself is stored after the entries, it's copied before the old
+            // buffer is released
+            let instance = allocator.cast::<usize>().read();
+            let entries = (*allocator)
+                .alloc_aligned::<T>((offset + mem::size_of::<usize>()) as u32, align as u32);
+            assert!(!entries.is_null(), "array allocation should not fail");
+            if self.capacity() != 0 {
+                ptr::copy_nonoverlapping(self.0.entries, entries, self.len() as usize);
+                (*allocator).free(self.0.entries);
+            }
+            entries
+                .cast::<u8>()
+                .add(offset)
+                .cast::<usize>()
+                .write(instance);
+            self.0.entries = entries;
+            self.0.capacity = cap;
+        }
+    }
 }
 
 impl<T: fmt::Debug> fmt::Debug for RedArray<T> {
@@ -257,12 +526,231 @@ impl<T> Drop for IntoIter<T> {
     }
 }
 
+/// A draining iterator over a range of elements of a [`RedArray`], created with
+/// [`RedArray::drain`].
+#[derive(Debug)]
+pub struct Drain<'a, T> {
+    array: &'a mut RedArray<T>,
+    ptr: *mut T,
+    end: *mut T,
+    tail_start: u32,
+    tail_len: u32,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ptr == self.end {
+            None
+        } else {
+            let old = self.ptr;
+            self.ptr = unsafe { old.add(1) };
+            Some(unsafe { ptr::read(old) })
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = unsafe { self.end.offset_from(self.ptr) } as usize;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.ptr == self.end {
+            None
+        } else {
+            self.end = unsafe { self.end.sub(1) };
+            Some(unsafe { ptr::read(self.end) })
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> FusedIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            let remaining = self.len();
+            if remaining > 0 {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.ptr, remaining));
+            }
+
+            let start = self.array.len();
+            if self.tail_len > 0 {
+                let entries = self.array.0.entries;
+                ptr::copy(
+                    entries.add(self.tail_start as usize),
+                    entries.add(start as usize),
+                    self.tail_len as usize,
+                );
+            }
+            self.array.0.size = start + self.tail_len;
+        }
+    }
+}
+
 fn get_allocator<T>(arr: &red::DynArray<T>) -> *mut IAllocator {
     if arr.capacity == 0 {
-        &arr.entries as *const _ as *mut _
+        // an array that has not been allocated yet can carry an allocator in place of the entries
+        if arr.entries.is_null() {
+            IAllocator::container() as *const _ as *mut _
+        } else {
+            &arr.entries as *const _ as *mut _
+        }
     } else {
         let end = unsafe { arr.entries.add(arr.capacity as _) } as usize;
         let aligned = end.next_multiple_of(mem::size_of::<usize>());
         aligned as _
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::RedArray;
+    use crate::types::allocator::mock;
+
+    fn array(items: &[i32]) -> RedArray<i32> {
+        items.iter().copied().collect()
+    }
+
+    #[test]
+    fn push_pop_and_grow() {
+        mock::install();
+        let mut arr = RedArray::new();
+        for i in 0..100 {
+            arr.push(i);
+        }
+        assert_eq!(arr.len(), 100);
+        assert!(arr.capacity() >= 100);
+        assert_eq!(arr.pop(), Some(99));
+        assert_eq!(arr.len(), 99);
+        assert_eq!(&arr[..3], &[0, 1, 2]);
+
+        let mut empty = RedArray::<i32>::new();
+        assert_eq!(empty.pop(), None);
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        mock::install();
+        let mut arr = array(&[1, 2, 4]);
+        arr.insert(2, 3);
+        arr.insert(0, 0);
+        arr.insert(5, 5);
+        assert_eq!(&*arr, &[0, 1, 2, 3, 4, 5]);
+
+        assert_eq!(arr.remove(0), 0);
+        assert_eq!(arr.remove(4), 5);
+        assert_eq!(&*arr, &[1, 2, 3, 4]);
+
+        assert_eq!(arr.swap_remove(0), 1);
+        assert_eq!(&*arr, &[4, 2, 3]);
+        assert_eq!(arr.swap_remove(2), 3);
+        assert_eq!(&*arr, &[4, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_out_of_bounds() {
+        mock::install();
+        array(&[1]).insert(2, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn remove_out_of_bounds() {
+        mock::install();
+        array(&[1]).remove(1);
+    }
+
+    #[test]
+    fn truncate_and_retain() {
+        mock::install();
+        let mut arr = array(&[1, 2, 3, 4, 5, 6]);
+        arr.truncate(10);
+        assert_eq!(arr.len(), 6);
+        arr.truncate(5);
+        assert_eq!(&*arr, &[1, 2, 3, 4, 5]);
+
+        arr.retain(|&x| x % 2 == 1);
+        assert_eq!(&*arr, &[1, 3, 5]);
+        arr.retain(|_| false);
+        assert!(arr.is_empty());
+    }
+
+    #[test]
+    fn drain_ranges() {
+        mock::install();
+        let mut arr = array(&[1, 2, 3, 4, 5]);
+        assert_eq!(arr.drain(1..3).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(&*arr, &[1, 4, 5]);
+
+        // unconsumed elements are dropped and the tail is moved back
+        let mut iter = arr.drain(..=1);
+        assert_eq!(iter.next(), Some(1));
+        drop(iter);
+        assert_eq!(&*arr, &[5]);
+
+        assert_eq!(arr.drain(..).collect::<Vec<_>>(), [5]);
+        assert!(arr.is_empty());
+        assert_eq!(arr.drain(..).count(), 0);
+    }
+
+    #[test]
+    fn extend_and_dedup() {
+        mock::install();
+        let mut arr = array(&[1, 1]);
+        arr.extend_from_slice(&[2, 2, 2, 3, 1, 1]);
+        arr.dedup();
+        assert_eq!(&*arr, &[1, 2, 3, 1]);
+
+        arr.dedup_by_key(|x| *x / 2);
+        assert_eq!(&*arr, &[1, 2, 1]);
+    }
+
+    #[test]
+    fn shrink_and_split() {
+        mock::install();
+        let mut arr = RedArray::with_capacity(16);
+        arr.extend_from_slice(&[1, 2, 3, 4]);
+        arr.shrink_to_fit();
+        assert_eq!(arr.capacity(), 4);
+        assert_eq!(&*arr, &[1, 2, 3, 4]);
+
+        let tail = arr.split_off(1);
+        assert_eq!(&*arr, &[1]);
+        assert_eq!(&*tail, &[2, 3, 4]);
+        assert!(arr.split_off(1).is_empty());
+
+        arr.clear();
+        arr.shrink_to_fit();
+        assert_eq!(arr.capacity(), 0);
+    }
+
+    #[test]
+    fn drops_elements() {
+        mock::install();
+        let rc = Rc::new(());
+        let mut arr: RedArray<_> = (0..8).map(|_| rc.clone()).collect();
+        arr.truncate(6);
+        arr.remove(0);
+        arr.swap_remove(0);
+        arr.retain(|_| false);
+        arr.extend((0..4).map(|_| rc.clone()));
+        arr.drain(1..2);
+        drop(arr.split_off(2));
+        arr.dedup_by(|_, _| true);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(arr);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use super::bytecode::Decoder;
+use super::{CName, Op, StackFrame};
+
+/// A captured chain of script stack frames, starting with the innermost one.
+///
+/// # Example
+/// ```rust
+/// use red4rs::types::{ScriptBacktrace, StackFrame};
+///
+/// fn print_backtrace(frame: &StackFrame) {
+///     let backtrace = ScriptBacktrace::capture(frame);
+///     // do something with the backtrace, it implements Display
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ScriptBacktrace {
+    frames: Vec<BacktraceFrame>,
+}
+
+impl ScriptBacktrace {
+    /// Captures the backtrace of a stack frame and all of its parents.
+    pub fn capture(frame: &StackFrame) -> Self {
+        let frames = std::iter::once(frame)
+            .chain(frame.parent_iter())
+            .map(BacktraceFrame::new)
+            .collect();
+        Self { frames }
+    }
+
+    /// Returns the captured frames, starting with the innermost one.
+    #[inline]
+    pub fn frames(&self) -> &[BacktraceFrame] {
+        &self.frames
+    }
+}
+
+impl fmt::Display for ScriptBacktrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, frame) in self.frames.iter().enumerate() {
+            writeln!(f, "{i:>4}: {frame}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A single frame of a [`ScriptBacktrace`].
+#[derive(Debug, Clone)]
+pub struct BacktraceFrame {
+    function: CName,
+    class: Option<CName>,
+    line: Option<u16>,
+}
+
+impl BacktraceFrame {
+    fn new(frame: &StackFrame) -> Self {
+        let func = frame.func();
+        Self {
+            function: func.name(),
+            class: func.parent().map(|class| class.name()),
+            line: current_line(frame),
+        }
+    }
+
+    /// Returns the full name of the function.
+    #[inline]
+    pub fn function(&self) -> CName {
+        self.function
+    }
+
+    /// Returns the name of the class the function belongs to, if any.
+    #[inline]
+    pub fn class(&self) -> Option<CName> {
+        self.class
+    }
+
+    /// Returns the source line of the call made from this frame, if it could be determined.
+    #[inline]
+    pub fn line(&self) -> Option<u16> {
+        self.line
+    }
+}
+
+impl fmt::Display for BacktraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(class) = self.class {
+            write!(f, "{class}::")?;
+        }
+        write!(f, "{}", self.function)?;
+        if let Some(line) = self.line {
+            write!(f, " at line {line}")?;
+        }
+        Ok(())
+    }
+}
+
+// the bytecode only stores line numbers in call instructions, so the line is taken from the last
+// call that precedes the current position of the frame
+fn current_line(frame: &StackFrame) -> Option<u16> {
+    let offset = frame.code_offset()?;
+    Decoder::new(frame.func().bytecode())
+        .map_while(Result::ok)
+        .take_while(|instr| (instr.offset as usize) < offset)
+        .filter_map(|instr| match instr.op {
+            Op::InvokeStatic(call) => Some(call.line),
+            Op::InvokeVirtual(call) => Some(call.line),
+            _ => None,
+        })
+        .last()
+}
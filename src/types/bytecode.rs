@@ -1,15 +1,17 @@
-use std::mem;
+use std::{fmt, mem, slice};
 
 use sealed::sealed;
+use thiserror::Error;
 
-use super::{CName, Function, IScriptable, StackFrame};
+use super::{CName, Class, Enum, Function, IScriptable, Property, StackFrame, Type};
 use crate::VoidPtr;
 
 pub const OPCODE_SIZE: isize = 1;
 pub const CALL_INSTR_SIZE: isize = mem::size_of::<InvokeStatic>() as isize;
 
 /// A function pointer type for bytecode opcode handlers.
-pub type OpcodeHandler = unsafe extern "C" fn(Option<&IScriptable>, &StackFrame, VoidPtr, VoidPtr);
+pub type OpcodeHandler =
+    unsafe extern "C" fn(Option<&IScriptable>, &mut StackFrame, VoidPtr, VoidPtr);
 
 /// A trait for types that correspond to bytecode instructions.
 #[sealed]
@@ -17,30 +19,536 @@ pub trait Instr {
     const OPCODE: u8;
 }
 
+macro_rules! instructions {
+    ($($opcode:literal => $name:ident { $($field:ident: $ty:ty),* };)*) => {
+        $(
+            #[derive(Debug, Clone, Copy)]
+            #[repr(C, packed)]
+            pub struct $name {
+                $(pub $field: $ty),*
+            }
+
+            #[sealed]
+            impl Instr for $name {
+                const OPCODE: u8 = $opcode;
+            }
+        )*
+
+        /// A decoded bytecode instruction.
+        #[derive(Debug, Clone, Copy)]
+        pub enum Op<'a> {
+            $($name(&'a $name),)*
+            StringConst(&'a StringConst),
+            StartProfiling(&'a StartProfiling),
+        }
+
+        impl Op<'_> {
+            /// Returns the opcode of the instruction.
+            pub fn opcode(&self) -> u8 {
+                match self {
+                    $(Self::$name(_) => $opcode,)*
+                    Self::StringConst(_) => StringConst::OPCODE,
+                    Self::StartProfiling(_) => StartProfiling::OPCODE,
+                }
+            }
+
+            /// Returns the mnemonic of the instruction.
+            pub fn mnemonic(&self) -> &'static str {
+                match self {
+                    $(Self::$name(_) => stringify!($name),)*
+                    Self::StringConst(_) => "StringConst",
+                    Self::StartProfiling(_) => "StartProfiling",
+                }
+            }
+        }
+
+        impl fmt::Display for Op<'_> {
+            #[allow(unused_variables, unused_mut, unused_assignments)]
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.mnemonic())?;
+                match self {
+                    $(Self::$name(instr) => {
+                        let mut sep = " ";
+                        $(
+                            write!(f, "{sep}{}: ", stringify!($field))?;
+                            Operand::fmt(&{ instr.$field }, f)?;
+                            sep = ", ";
+                        )*
+                        Ok(())
+                    })*
+                    Self::StringConst(instr) => write!(f, " {:?}", String::from_utf8_lossy(instr.as_bytes())),
+                    Self::StartProfiling(instr) => write!(
+                        f,
+                        " {:?}, enabled: {}",
+                        String::from_utf8_lossy(instr.function_name()),
+                        instr.is_enabled()
+                    ),
+                }
+            }
+        }
+
+        fn decode_op(code: &[u8], pos: usize) -> Result<(Op<'_>, usize), DecodeError> {
+            let offset = pos as u32;
+            let opcode = *code.get(pos).ok_or(DecodeError::UnexpectedEof { offset })?;
+            let pos = pos + OPCODE_SIZE as usize;
+            match opcode {
+                $($opcode => {
+                    let instr = read::<$name>(code, pos, offset)?;
+                    Ok((Op::$name(instr), pos + mem::size_of::<$name>()))
+                })*
+                StringConst::OPCODE => {
+                    let instr = read::<StringConst>(code, pos, offset)?;
+                    let end = pos + mem::size_of::<StringConst>() + instr.len();
+                    check_bounds(code, end, offset)?;
+                    Ok((Op::StringConst(instr), end))
+                }
+                StartProfiling::OPCODE => {
+                    let instr = read::<StartProfiling>(code, pos, offset)?;
+                    let end = pos + mem::size_of::<StartProfiling>() + instr.name_len() + 1;
+                    check_bounds(code, end, offset)?;
+                    Ok((Op::StartProfiling(instr), end))
+                }
+                opcode => Err(DecodeError::UnknownOpcode { opcode, offset }),
+            }
+        }
+    };
+}
+
+// the layouts below describe instructions as they appear in memory after the game has loaded
+// the scripts, pool indices are resolved into pointers at that point
+instructions! {
+    0 => Nop {};
+    1 => Null {};
+    2 => I32One {};
+    3 => I32Zero {};
+    4 => I8Const { value: i8 };
+    5 => I16Const { value: i16 };
+    6 => I32Const { value: i32 };
+    7 => I64Const { value: i64 };
+    8 => U8Const { value: u8 };
+    9 => U16Const { value: u16 };
+    10 => U32Const { value: u32 };
+    11 => U64Const { value: u64 };
+    12 => F32Const { value: f32 };
+    13 => F64Const { value: f64 };
+    14 => CNameConst { value: CName };
+    15 => EnumConst { enum_: *const Enum, value: i64 };
+    17 => TweakDbIdConst { value: u64 };
+    18 => ResourceConst { value: u64 };
+    19 => TrueConst {};
+    20 => FalseConst {};
+    21 => Breakpoint { line: u16, line_start: u32, column: u16, length: u16, enabled: u8, padding: u64 };
+    22 => Assign {};
+    23 => Target { offset: i16 };
+    24 => Local { property: *const Property };
+    25 => Param { property: *const Property };
+    26 => ObjectField { property: *const Property };
+    27 => ExternalVar {};
+    28 => Switch { type_: *const Type, first_case: i16 };
+    29 => SwitchLabel { next_case: i16, body: i16 };
+    30 => SwitchDefault {};
+    31 => Jump { offset: i16 };
+    32 => JumpIfFalse { offset: i16 };
+    33 => Skip { offset: i16 };
+    34 => Conditional { false_branch: i16, end: i16 };
+    35 => Construct { arg_count: u8, class: *const Class };
+    36 => InvokeStatic { skip: u16, line: u16, func: *mut Function, flags: u16 };
+    37 => InvokeVirtual { skip: u16, line: u16, name: CName, flags: u16 };
+    38 => ParamEnd {};
+    39 => Return {};
+    40 => StructField { property: *const Property };
+    41 => Context { skip: u16 };
+    42 => Equals { type_: *const Type };
+    43 => RefStringEqualsString {};
+    44 => StringEqualsRefString {};
+    45 => NotEquals { type_: *const Type };
+    46 => RefStringNotEqualsString {};
+    47 => StringNotEqualsRefString {};
+    48 => New { class: *const Class };
+    49 => Delete {};
+    50 => This {};
+    52 => ArrayClear { type_: *const Type };
+    53 => ArraySize { type_: *const Type };
+    54 => ArrayResize { type_: *const Type };
+    55 => ArrayFindFirst { type_: *const Type };
+    56 => ArrayFindFirstFast { type_: *const Type };
+    57 => ArrayFindLast { type_: *const Type };
+    58 => ArrayFindLastFast { type_: *const Type };
+    59 => ArrayContains { type_: *const Type };
+    60 => ArrayContainsFast { type_: *const Type };
+    61 => ArrayCount { type_: *const Type };
+    62 => ArrayCountFast { type_: *const Type };
+    63 => ArrayPush { type_: *const Type };
+    64 => ArrayPop { type_: *const Type };
+    65 => ArrayInsert { type_: *const Type };
+    66 => ArrayRemove { type_: *const Type };
+    67 => ArrayRemoveFast { type_: *const Type };
+    68 => ArrayGrow { type_: *const Type };
+    69 => ArrayErase { type_: *const Type };
+    70 => ArrayEraseFast { type_: *const Type };
+    71 => ArrayLast { type_: *const Type };
+    72 => ArrayElement { type_: *const Type };
+    73 => ArraySort { type_: *const Type };
+    74 => ArraySortByPredicate { type_: *const Type };
+    75 => StaticArraySize { type_: *const Type };
+    76 => StaticArrayFindFirst { type_: *const Type };
+    77 => StaticArrayFindFirstFast { type_: *const Type };
+    78 => StaticArrayFindLast { type_: *const Type };
+    79 => StaticArrayFindLastFast { type_: *const Type };
+    80 => StaticArrayContains { type_: *const Type };
+    81 => StaticArrayContainsFast { type_: *const Type };
+    82 => StaticArrayCount { type_: *const Type };
+    83 => StaticArrayCountFast { type_: *const Type };
+    84 => StaticArrayLast { type_: *const Type };
+    85 => StaticArrayElement { type_: *const Type };
+    86 => RefToBool {};
+    87 => WeakRefToBool {};
+    88 => EnumToI32 { type_: *const Type, size: u8 };
+    89 => I32ToEnum { type_: *const Type, size: u8 };
+    90 => DynamicCast { class: *const Class, flags: u8 };
+    91 => ToStr { type_: *const Type };
+    92 => ToVariant { type_: *const Type };
+    93 => FromVariant { type_: *const Type };
+    94 => VariantIsDefined {};
+    95 => VariantIsRef {};
+    96 => VariantIsArray {};
+    97 => VariantTypeName {};
+    98 => VariantToString {};
+    99 => WeakRefToRef {};
+    100 => RefToWeakRef {};
+    101 => WeakRefNull {};
+    102 => AsRef { type_: *const Type };
+    103 => Deref { type_: *const Type };
+}
+
+/// A string constant, the header is followed by the bytes of the string.
 #[derive(Debug)]
-#[repr(packed)]
-pub struct InvokeStatic {
-    pub skip: u16,
-    pub line: u16,
-    pub func: *mut Function,
-    pub flags: u16,
+#[repr(C, packed)]
+pub struct StringConst {
+    len: u32,
+}
+
+impl StringConst {
+    /// Returns the length of the string in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns `true` if the string is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bytes of the string.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts((self as *const Self).add(1).cast::<u8>(), self.len()) }
+    }
 }
 
 #[sealed]
-impl Instr for InvokeStatic {
-    const OPCODE: u8 = 36;
+impl Instr for StringConst {
+    const OPCODE: u8 = 16;
 }
 
+/// A profiling marker, the header is followed by the name of the profiled function and a flag
+/// indicating whether profiling is enabled.
 #[derive(Debug)]
-#[repr(packed)]
-pub struct InvokeVirtual {
-    pub skip: u16,
-    pub line: u16,
-    pub name: CName,
-    pub flags: u16,
+#[repr(C, packed)]
+pub struct StartProfiling {
+    name_len: u32,
+}
+
+impl StartProfiling {
+    /// Returns the name of the profiled function.
+    #[inline]
+    pub fn function_name(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.name_ptr(), self.name_len()) }
+    }
+
+    /// Returns `true` if profiling is enabled.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        unsafe { self.name_ptr().add(self.name_len()).read() != 0 }
+    }
+
+    #[inline]
+    fn name_len(&self) -> usize {
+        self.name_len as usize
+    }
+
+    #[inline]
+    fn name_ptr(&self) -> *const u8 {
+        unsafe { (self as *const Self).add(1).cast::<u8>() }
+    }
 }
 
 #[sealed]
-impl Instr for InvokeVirtual {
-    const OPCODE: u8 = 37;
+impl Instr for StartProfiling {
+    const OPCODE: u8 = 51;
+}
+
+/// A decoded instruction along with its offset in the code buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct Instruction<'a> {
+    pub offset: u32,
+    pub op: Op<'a>,
+}
+
+impl fmt::Display for Instruction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>5}: {}", self.offset, self.op)
+    }
+}
+
+/// An iterator decoding instructions from a code buffer.
+#[derive(Debug, Clone)]
+pub struct Decoder<'a> {
+    code: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Creates a new decoder for the given code buffer.
+    #[inline]
+    pub fn new(code: &'a [u8]) -> Self {
+        Self { code, pos: 0 }
+    }
+
+    /// Creates a new decoder starting at the specified offset of the code buffer.
+    #[inline]
+    pub fn at(code: &'a [u8], offset: usize) -> Self {
+        Self { code, pos: offset }
+    }
+}
+
+impl<'a> Iterator for Decoder<'a> {
+    type Item = Result<Instruction<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.code.len() {
+            return None;
+        }
+        let offset = self.pos as u32;
+        match decode_op(self.code, self.pos) {
+            Ok((op, next)) => {
+                self.pos = next;
+                Some(Ok(Instruction { offset, op }))
+            }
+            Err(err) => {
+                // stop after the first error
+                self.pos = self.code.len();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Decodes all instructions in a code buffer.
+#[inline]
+pub fn decode(code: &[u8]) -> Result<Vec<Instruction<'_>>, DecodeError> {
+    Decoder::new(code).collect()
+}
+
+/// Decodes a code buffer into a textual listing with one instruction per line.
+pub fn disassemble(code: &[u8]) -> Result<String, DecodeError> {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    for instr in Decoder::new(code) {
+        writeln!(out, "{}", instr?).expect("writing to a string should not fail");
+    }
+    Ok(out)
+}
+
+/// Finds the offset of the param end instruction that terminates the argument list the code at
+/// `offset` belongs to. Arguments of nested calls are skipped along with their own param ends.
+pub(crate) fn param_end_offset(code: &[u8], offset: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for instr in Decoder::at(code, offset) {
+        let instr = instr.ok()?;
+        match instr.op {
+            Op::InvokeStatic(_) | Op::InvokeVirtual(_) => depth += 1,
+            Op::ParamEnd(_) if depth == 0 => return Some(instr.offset as usize),
+            Op::ParamEnd(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// An error returned when decoding bytecode fails.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("unknown opcode {opcode} at offset {offset}")]
+    UnknownOpcode { opcode: u8, offset: u32 },
+    #[error("unexpected end of code in instruction at offset {offset}")]
+    UnexpectedEof { offset: u32 },
+}
+
+#[inline]
+fn check_bounds(code: &[u8], end: usize, offset: u32) -> Result<(), DecodeError> {
+    if end > code.len() {
+        return Err(DecodeError::UnexpectedEof { offset });
+    }
+    Ok(())
+}
+
+#[inline]
+fn read<I: Instr>(code: &[u8], pos: usize, offset: u32) -> Result<&I, DecodeError> {
+    check_bounds(code, pos + mem::size_of::<I>(), offset)?;
+    // all instructions are packed, so they have an alignment of 1
+    Ok(unsafe { &*code.as_ptr().add(pos).cast::<I>() })
+}
+
+trait Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+macro_rules! impl_display_operand {
+    ($($ty:ty),*) => {$(
+        impl Operand for $ty {
+            #[inline]
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(self, f)
+            }
+        }
+    )*};
+}
+
+impl_display_operand!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+impl<T> Operand for *const T {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(self, f)
+    }
+}
+
+impl<T> Operand for *mut T {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(self, f)
+    }
+}
+
+impl Operand for CName {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // names are not resolved, because the name pool is only available in-game
+        write!(f, "{:#x}", u64::from(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_fixture() {
+        #[rustfmt::skip]
+        let code = [
+            41, 0x16, 0x00,                         // Context
+            50,                                     // This
+            36, 0x0d, 0x00, 0x07, 0x00,             // InvokeStatic
+                0x00, 0x10, 0, 0, 0, 0, 0, 0,
+                0x01, 0x00,
+            6, 0xff, 0xff, 0xff, 0xff,              // I32Const
+            16, 3, 0, 0, 0, b'a', b'b', b'c',       // StringConst
+            38,                                     // ParamEnd
+            32, 0x02, 0x00,                         // JumpIfFalse
+            19,                                     // TrueConst
+            39,                                     // Return
+        ];
+
+        let instrs = decode(&code).unwrap();
+        let offsets: Vec<_> = instrs.iter().map(|instr| instr.offset).collect();
+        assert_eq!(offsets, [0, 3, 4, 19, 24, 32, 33, 36, 37]);
+
+        let Op::InvokeStatic(call) = instrs[2].op else {
+            panic!("expected InvokeStatic, got {:?}", instrs[2].op);
+        };
+        assert_eq!({ call.skip }, 13);
+        assert_eq!({ call.line }, 7);
+        assert_eq!({ call.func } as usize, 0x1000);
+        assert_eq!({ call.flags }, 1);
+
+        let Op::I32Const(int) = instrs[3].op else {
+            panic!("expected I32Const, got {:?}", instrs[3].op);
+        };
+        assert_eq!({ int.value }, -1);
+
+        let Op::StringConst(str) = instrs[4].op else {
+            panic!("expected StringConst, got {:?}", instrs[4].op);
+        };
+        assert_eq!(str.as_bytes(), b"abc");
+
+        assert_eq!(instrs[8].op.opcode(), Return::OPCODE);
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(
+            decode(&[0, 0, 200]).unwrap_err(),
+            DecodeError::UnknownOpcode {
+                opcode: 200,
+                offset: 2
+            }
+        );
+        assert_eq!(
+            decode(&[0, 6, 1, 2]).unwrap_err(),
+            DecodeError::UnexpectedEof { offset: 1 }
+        );
+        assert_eq!(
+            decode(&[16, 4, 0, 0, 0, b'a']).unwrap_err(),
+            DecodeError::UnexpectedEof { offset: 0 }
+        );
+        assert!(decode(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn param_end() {
+        #[rustfmt::skip]
+        let code = [
+            6, 0x01, 0x00, 0x00, 0x00,              // I32Const
+            36, 0x0d, 0x00, 0x07, 0x00,             // InvokeStatic
+                0x00, 0x10, 0, 0, 0, 0, 0, 0,
+                0x01, 0x00,
+            2,                                      // I32One
+            38,                                     // ParamEnd
+            19,                                     // TrueConst
+            38,                                     // ParamEnd
+            39,                                     // Return
+        ];
+
+        assert_eq!(param_end_offset(&code, 0), Some(23));
+        assert_eq!(param_end_offset(&code, 5), Some(23));
+        assert_eq!(param_end_offset(&code, 20), Some(21));
+        assert_eq!(param_end_offset(&code, 23), Some(23));
+        assert_eq!(param_end_offset(&code, 24), None);
+        assert_eq!(param_end_offset(&[200, 38], 0), None);
+    }
+
+    #[test]
+    fn disassemble_fixture() {
+        #[rustfmt::skip]
+        let code = [
+            24, 0x20, 0, 0, 0, 0, 0, 0, 0,          // Local
+            5, 0x2a, 0x00,                          // I16Const
+            22,                                     // Assign
+            51, 1, 0, 0, 0, b'f', 1,                // StartProfiling
+            31, 0xfd, 0xff,                         // Jump
+        ];
+
+        let expected = concat!(
+            "    0: Local property: 0x20\n",
+            "    9: I16Const value: 42\n",
+            "   12: Assign\n",
+            "   13: StartProfiling \"f\", enabled: true\n",
+            "   20: Jump offset: -3\n",
+        );
+        assert_eq!(disassemble(&code).unwrap(), expected);
+    }
 }
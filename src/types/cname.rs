@@ -2,11 +2,12 @@ use std::ffi::{self, CStr};
 use std::fmt;
 use std::hash::Hash;
 
+use super::HashRegistry;
 use crate::fnv1a64;
 use crate::raw::root::RED4ext as red;
 
 /// A hash representing an immutable string stored in a global name pool.
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Default, Clone, Copy)]
 #[repr(transparent)]
 pub struct CName(red::CName);
 
@@ -45,6 +46,34 @@ impl CName {
             .to_str()
             .unwrap()
     }
+
+    /// Returns the string representation of the [`CName`] from the global name pool, or
+    /// [`None`] if the name is not in the pool or is not valid UTF-8.
+    pub fn try_as_str(&self) -> Option<&'static str> {
+        let str = unsafe { self.0.ToString() };
+        if str.is_null() {
+            return None;
+        }
+        unsafe { ffi::CStr::from_ptr(str) }.to_str().ok()
+    }
+
+    // names that are neither registered nor resolved by the lookup are written as hashes
+    fn fmt_with(
+        self,
+        f: &mut fmt::Formatter<'_>,
+        lookup: impl FnOnce(CName) -> Option<&'static str>,
+    ) -> fmt::Result {
+        if self.0.hash == 0 {
+            return f.write_str("None");
+        }
+        match HashRegistry::global()
+            .resolve_name(self)
+            .or_else(|| lookup(self))
+        {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{:#018X}", self.0.hash),
+        }
+    }
 }
 
 impl From<u64> for CName {
@@ -59,17 +88,20 @@ impl From<CName> for u64 {
     }
 }
 
+impl fmt::Debug for CName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match HashRegistry::global().resolve_name(*self) {
+            Some(name) => f.debug_tuple("CName").field(&name).finish(),
+            None if self.0.hash == 0 => f.write_str("CName(None)"),
+            None => write!(f, "CName({:#018X})", self.0.hash),
+        }
+    }
+}
+
 impl std::fmt::Display for CName {
+    #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            if self.0.hash == 0 {
-                "None"
-            } else {
-                self.as_str()
-            }
-        )
+        self.fmt_with(f, |name| name.try_as_str())
     }
 }
 
@@ -136,4 +168,23 @@ mod test {
         assert_eq!(u64::from(CName::new("None")), 0);
         assert_eq!(u64::from(CName::new("")), 0xCBF2_9CE4_8422_2325);
     }
+
+    #[test]
+    fn display_without_name_pool() {
+        struct Offline(CName);
+
+        impl fmt::Display for Offline {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt_with(f, |_| None)
+            }
+        }
+
+        let registered = HashRegistry::global().register_name("gameObject");
+        assert_eq!(Offline(registered).to_string(), "gameObject");
+        assert_eq!(Offline(CName::undefined()).to_string(), "None");
+        assert_eq!(
+            Offline(CName::from(0x1234)).to_string(),
+            "0x0000000000001234"
+        );
+    }
 }
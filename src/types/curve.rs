@@ -0,0 +1,574 @@
+use std::marker::PhantomData;
+use std::{fmt, mem, slice};
+
+use super::{DataBuffer, RedArray};
+use crate::raw::root::RED4ext as red;
+
+/// The interpolation used between consecutive points of a curve, as defined by the engine's
+/// `EInterpolationType` enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Interpolation {
+    /// The value of the preceding point is held until the next point.
+    Constant = 0,
+    /// The value changes linearly between points.
+    Linear = 1,
+    /// The value follows a quadratic Bezier curve between points, with the control point
+    /// placed along the Catmull-Rom tangent of the preceding point.
+    BezierQuadratic = 2,
+    /// The value follows a cubic Bezier curve between points, with the control points placed
+    /// along the Catmull-Rom tangents of both points.
+    BezierCubic = 3,
+    /// The value follows a cubic Hermite spline through the points, with finite difference
+    /// tangents.
+    Hermite = 4,
+}
+
+impl TryFrom<u8> for Interpolation {
+    type Error = u8;
+
+    #[inline]
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Constant),
+            1 => Ok(Self::Linear),
+            2 => Ok(Self::BezierQuadratic),
+            3 => Ok(Self::BezierCubic),
+            4 => Ok(Self::Hermite),
+            other => Err(other),
+        }
+    }
+}
+
+/// A trait for values that can be interpolated along a curve.
+pub trait Interpolate: Copy {
+    /// Returns `self * a + other * b`.
+    fn blend(self, a: f32, other: Self, b: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    #[inline]
+    fn blend(self, a: f32, other: Self, b: f32) -> Self {
+        self * a + other * b
+    }
+}
+
+impl Interpolate for f64 {
+    #[inline]
+    fn blend(self, a: f32, other: Self, b: f32) -> Self {
+        self * f64::from(a) + other * f64::from(b)
+    }
+}
+
+impl<const N: usize> Interpolate for [f32; N] {
+    #[inline]
+    fn blend(self, a: f32, other: Self, b: f32) -> Self {
+        let mut res = self;
+        for (lhs, rhs) in res.iter_mut().zip(other) {
+            *lhs = lhs.blend(a, rhs, b);
+        }
+        res
+    }
+}
+
+/// A single point of a [`Curve`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct CurvePoint<T> {
+    time: f32,
+    value: T,
+}
+
+impl<T> CurvePoint<T> {
+    #[inline]
+    pub fn new(time: f32, value: T) -> Self {
+        Self { time, value }
+    }
+
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    #[inline]
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+}
+
+/// A curve with a single channel of values of type `T`.
+#[repr(transparent)]
+pub struct Curve<T>(red::CurveData, PhantomData<T>);
+
+impl<T> Curve<T> {
+    /// Returns the points of the curve, ordered by time.
+    #[inline]
+    pub fn points(&self) -> &[CurvePoint<T>] {
+        self.point_array()
+    }
+
+    /// Returns the number of points in the curve.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.point_array().len()
+    }
+
+    /// Returns `true` if the curve has no points.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.point_array().is_empty()
+    }
+
+    /// Returns the interpolation of the curve, or `None` if it's not recognized.
+    #[inline]
+    pub fn interpolation(&self) -> Option<Interpolation> {
+        Interpolation::try_from(self.0.interpolationType).ok()
+    }
+
+    #[inline]
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.0.interpolationType = interpolation as u8;
+    }
+
+    /// Inserts a point keeping the points ordered by time. A point with the same time as
+    /// existing ones is placed after them.
+    pub fn insert(&mut self, time: f32, value: T) {
+        let points = self.point_array_mut();
+        let index = points.partition_point(|point| point.time <= time);
+        points.insert(index as u32, CurvePoint::new(time, value));
+    }
+
+    /// Removes and returns the point at `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn remove(&mut self, index: u32) -> CurvePoint<T> {
+        self.point_array_mut().remove(index)
+    }
+
+    #[inline]
+    fn point_array(&self) -> &RedArray<CurvePoint<T>> {
+        unsafe { &*(&self.0.points as *const _ as *const RedArray<CurvePoint<T>>) }
+    }
+
+    #[inline]
+    fn point_array_mut(&mut self) -> &mut RedArray<CurvePoint<T>> {
+        unsafe { &mut *(&mut self.0.points as *mut _ as *mut RedArray<CurvePoint<T>>) }
+    }
+}
+
+impl<T: Interpolate> Curve<T> {
+    /// Returns the value of the curve at `time`. Times outside of the curve are clamped to
+    /// its first and last point. Returns `None` if the curve has no points or if its
+    /// interpolation is not recognized.
+    #[inline]
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let points = self.points();
+        sample(
+            self.interpolation()?,
+            points.len(),
+            |i| points[i].time,
+            |i| points[i].value,
+            time,
+        )
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Curve<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Curve")
+            .field("interpolation", &self.interpolation())
+            .field("points", &self.points())
+            .finish()
+    }
+}
+
+/// A curve with multiple channels of values of type `T`. The points are stored in a single
+/// buffer, each one made of its time followed by a value for every channel.
+#[repr(C)]
+pub struct MultiChannelCurve<T> {
+    num_channels: u32,
+    interpolation: u8,
+    _unk05: u8,
+    _unk08: u32,
+    buffer: DataBuffer,
+    phantom: PhantomData<T>,
+}
+
+const _: () = assert!(mem::size_of::<MultiChannelCurve<f32>>() == 56);
+
+impl<T> MultiChannelCurve<T> {
+    /// Returns the number of channels of the curve.
+    #[inline]
+    pub fn num_channels(&self) -> u32 {
+        self.num_channels
+    }
+
+    /// Returns the number of points in the curve.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        (self.buffer.len() as usize / self.stride()) as u32
+    }
+
+    /// Returns `true` if the curve has no points.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the interpolation of the curve, or `None` if it's not recognized.
+    #[inline]
+    pub fn interpolation(&self) -> Option<Interpolation> {
+        Interpolation::try_from(self.interpolation).ok()
+    }
+
+    #[inline]
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation as u8;
+    }
+
+    /// Returns the time and the channel values of the point at `index`.
+    pub fn point(&self, index: u32) -> Option<(f32, &[T])> {
+        if index >= self.len() {
+            return None;
+        }
+        let offset = index as usize * self.stride();
+        let bytes = &self.buffer.as_bytes()[offset..offset + self.stride()];
+        let time = f32::from_ne_bytes(bytes[..4].try_into().unwrap());
+        let values = unsafe {
+            slice::from_raw_parts(
+                bytes[Self::header_size()..].as_ptr().cast::<T>(),
+                self.num_channels as usize,
+            )
+        };
+        Some((time, values))
+    }
+
+    /// Returns an iterator over the points of the curve.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (f32, &[T])> {
+        (0..self.len()).filter_map(|i| self.point(i))
+    }
+
+    #[inline]
+    fn time(&self, index: usize) -> f32 {
+        let offset = index * self.stride();
+        f32::from_ne_bytes(
+            self.buffer.as_bytes()[offset..offset + 4]
+                .try_into()
+                .unwrap(),
+        )
+    }
+
+    // the time is padded to the alignment of the values
+    #[inline]
+    fn header_size() -> usize {
+        mem::align_of::<T>().max(mem::size_of::<f32>())
+    }
+
+    #[inline]
+    fn stride(&self) -> usize {
+        let align = Self::header_size();
+        let size = Self::header_size() + self.num_channels as usize * mem::size_of::<T>();
+        size.next_multiple_of(align)
+    }
+}
+
+impl<T: Interpolate> MultiChannelCurve<T> {
+    /// Returns the values of every channel at `time`. Times outside of the curve are clamped
+    /// to its first and last point. Returns `None` in the same cases as [`Curve::sample`].
+    pub fn sample(&self, time: f32) -> Option<Vec<T>> {
+        let interpolation = self.interpolation()?;
+        let len = self.len() as usize;
+        (0..self.num_channels as usize)
+            .map(|channel| {
+                sample(
+                    interpolation,
+                    len,
+                    |i| self.time(i),
+                    |i| self.point(i as u32).unwrap().1[channel],
+                    time,
+                )
+            })
+            .collect()
+    }
+
+    /// Inserts a point keeping the points ordered by time. A point with the same time as
+    /// existing ones is placed after them.
+    ///
+    /// # Panics
+    /// Panics if the number of values does not match the number of channels.
+    pub fn insert(&mut self, time: f32, values: &[T]) {
+        assert_eq!(
+            values.len(),
+            self.num_channels as usize,
+            "the number of values should match the number of channels"
+        );
+        let index = (0..self.len() as usize)
+            .position(|i| self.time(i) > time)
+            .unwrap_or(self.len() as usize);
+
+        let offset = index * self.stride();
+        let mut point = vec![0u8; self.stride()];
+        point[..4].copy_from_slice(&time.to_ne_bytes());
+        let values = unsafe {
+            slice::from_raw_parts(values.as_ptr().cast::<u8>(), mem::size_of_val(values))
+        };
+        point[Self::header_size()..Self::header_size() + values.len()].copy_from_slice(values);
+
+        let mut bytes = self.buffer.as_bytes().to_vec();
+        bytes.splice(offset..offset, point);
+        self.buffer = DataBuffer::from(bytes);
+    }
+
+    /// Removes the point at `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: u32) {
+        assert!(index < self.len(), "index out of bounds");
+        let offset = index as usize * self.stride();
+        let mut bytes = self.buffer.as_bytes().to_vec();
+        bytes.drain(offset..offset + self.stride());
+        self.buffer = DataBuffer::from(bytes);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MultiChannelCurve<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiChannelCurve")
+            .field("num_channels", &self.num_channels)
+            .field("interpolation", &self.interpolation())
+            .field("points", &self.iter().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn sample<T, F, G>(
+    interpolation: Interpolation,
+    len: usize,
+    time_at: F,
+    value_at: G,
+    time: f32,
+) -> Option<T>
+where
+    T: Interpolate,
+    F: Fn(usize) -> f32,
+    G: Fn(usize) -> T,
+{
+    let last = len.checked_sub(1)?;
+    if len == 1 || time <= time_at(0) {
+        return Some(value_at(0));
+    }
+    if time >= time_at(last) {
+        return Some(value_at(last));
+    }
+
+    // find the first point past the time, there's always one since the time is in range
+    let (mut lo, mut hi) = (1, last);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if time_at(mid) <= time {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    let (start, end) = (lo - 1, lo);
+
+    let (t0, t1) = (time_at(start), time_at(end));
+    let span = t1 - t0;
+    if span <= 0.0 {
+        return Some(value_at(end));
+    }
+    let s = (time - t0) / span;
+    let (p0, p1) = (value_at(start), value_at(end));
+
+    // the points carry no tangents, the smooth modes derive them from the neighbouring points
+    // as a change of value per unit of time
+    let slope = |from: usize, to: usize| {
+        let dt = time_at(to) - time_at(from);
+        let dt = if dt > 0.0 { dt } else { f32::INFINITY };
+        value_at(to).blend(1.0 / dt, value_at(from), -1.0 / dt)
+    };
+    let tangent = |i: usize| match interpolation {
+        // finite differences, the average of the slopes on both sides of the point
+        Interpolation::Hermite if i > 0 && i < last => {
+            slope(i - 1, i).blend(0.5, slope(i, i + 1), 0.5)
+        }
+        // Catmull-Rom, the slope of the chord between the neighbours of the point
+        _ => slope(i.saturating_sub(1), (i + 1).min(last)),
+    };
+
+    let res = match interpolation {
+        Interpolation::Constant => p0,
+        Interpolation::Linear => p0.blend(1.0 - s, p1, s),
+        Interpolation::BezierQuadratic => {
+            // the control point follows the tangent of the starting point
+            let c = p0.blend(1.0, tangent(start), span / 2.0);
+            let (b0, b1, b2) = ((1.0 - s) * (1.0 - s), 2.0 * s * (1.0 - s), s * s);
+            p0.blend(b0, c, b1).blend(1.0, p1, b2)
+        }
+        Interpolation::BezierCubic => {
+            let c0 = p0.blend(1.0, tangent(start), span / 3.0);
+            let c1 = p1.blend(1.0, tangent(end), -span / 3.0);
+            let r = 1.0 - s;
+            let (b0, b1, b2, b3) = (r * r * r, 3.0 * s * r * r, 3.0 * s * s * r, s * s * s);
+            p0.blend(b0, c0, b1).blend(1.0, c1.blend(b2, p1, b3), 1.0)
+        }
+        Interpolation::Hermite => {
+            let (s2, s3) = (s * s, s * s * s);
+            let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+            let h10 = s3 - 2.0 * s2 + s;
+            let h01 = -2.0 * s3 + 3.0 * s2;
+            let h11 = s3 - s2;
+            let tangents = tangent(start).blend(h10 * span, tangent(end), h11 * span);
+            p0.blend(h00, p1, h01).blend(1.0, tangents, 1.0)
+        }
+    };
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sample, Interpolate, Interpolation};
+
+    fn sample_points<T: Interpolate>(
+        interpolation: Interpolation,
+        points: &[(f32, T)],
+        time: f32,
+    ) -> Option<T> {
+        sample(
+            interpolation,
+            points.len(),
+            |i| points[i].0,
+            |i| points[i].1,
+            time,
+        )
+    }
+
+    #[test]
+    fn clamps_and_handles_empty() {
+        let points = [(1.0, 2.0f32), (2.0, 4.0)];
+        assert_eq!(sample_points::<f32>(Interpolation::Linear, &[], 0.0), None);
+        assert_eq!(
+            sample_points(Interpolation::Linear, &points[..1], 5.0),
+            Some(2.0)
+        );
+        assert_eq!(
+            sample_points(Interpolation::Linear, &points, 0.0),
+            Some(2.0)
+        );
+        assert_eq!(
+            sample_points(Interpolation::Linear, &points, 3.0),
+            Some(4.0)
+        );
+    }
+
+    #[test]
+    fn constant_and_linear() {
+        let points = [(0.0, 0.0f32), (1.0, 10.0), (3.0, 20.0)];
+        assert_eq!(
+            sample_points(Interpolation::Constant, &points, 0.5),
+            Some(0.0)
+        );
+        assert_eq!(
+            sample_points(Interpolation::Constant, &points, 2.9),
+            Some(10.0)
+        );
+        assert_eq!(
+            sample_points(Interpolation::Linear, &points, 0.25),
+            Some(2.5)
+        );
+        assert_eq!(
+            sample_points(Interpolation::Linear, &points, 2.0),
+            Some(15.0)
+        );
+    }
+
+    fn assert_samples<const N: usize>(
+        interpolation: Interpolation,
+        points: &[(f32, f32)],
+        expected: [(f32, f32); N],
+    ) {
+        for (time, value) in expected {
+            let res = sample_points(interpolation, points, time).unwrap();
+            assert!(
+                (res - value).abs() < 1e-5,
+                "{interpolation:?} at {time}: expected {value}, got {res}"
+            );
+        }
+    }
+
+    #[test]
+    fn bezier_quadratic() {
+        let points = [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)];
+        assert_samples(
+            Interpolation::BezierQuadratic,
+            &points,
+            [(0.5, 0.5), (1.0, 1.0), (1.25, 0.9375), (1.5, 0.75)],
+        );
+    }
+
+    #[test]
+    fn bezier_cubic() {
+        let points = [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)];
+        assert_samples(
+            Interpolation::BezierCubic,
+            &points,
+            [(0.5, 0.625), (1.0, 1.0), (1.5, 0.625)],
+        );
+        let points = [(0.0, 0.0), (1.0, 1.0), (3.0, 0.0)];
+        assert_samples(
+            Interpolation::BezierCubic,
+            &points,
+            [(0.5, 0.625), (2.0, 0.625)],
+        );
+    }
+
+    #[test]
+    fn hermite() {
+        let points = [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)];
+        assert_samples(
+            Interpolation::Hermite,
+            &points,
+            [(0.5, 0.625), (1.0, 1.0), (1.5, 0.625)],
+        );
+        // unlike the Catmull-Rom tangents, the finite differences depend on the spacing
+        let points = [(0.0, 0.0), (1.0, 1.0), (3.0, 0.0)];
+        assert_samples(
+            Interpolation::Hermite,
+            &points,
+            [(0.5, 0.59375), (2.0, 0.6875)],
+        );
+    }
+
+    #[test]
+    fn smooth_modes_keep_lines() {
+        let points = [(0.0, 0.0), (1.0, 2.0), (3.0, 6.0), (4.0, 8.0)];
+        for interpolation in [
+            Interpolation::BezierQuadratic,
+            Interpolation::BezierCubic,
+            Interpolation::Hermite,
+        ] {
+            assert_samples(interpolation, &points, [(0.5, 1.0), (2.0, 4.0), (3.5, 7.0)]);
+        }
+        assert_eq!(Interpolation::try_from(4), Ok(Interpolation::Hermite));
+        assert_eq!(Interpolation::try_from(5), Err(5));
+    }
+
+    #[test]
+    fn multiple_components() {
+        let points = [(0.0, [0.0f32, 10.0]), (2.0, [1.0, 20.0])];
+        assert_eq!(
+            sample_points(Interpolation::Linear, &points, 1.0),
+            Some([0.5, 15.0])
+        );
+        let points = [(0.0, 0.0f64), (2.0, 1.0)];
+        assert_eq!(
+            sample_points(Interpolation::Linear, &points, 0.5),
+            Some(0.25)
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::{fmt, ptr, slice};
+
+use super::{IAllocator, JobHandle, SharedPtr};
+use crate::raw::root::RED4ext as red;
+
+const BUFFER_ALIGNMENT: u32 = 16;
+
+/// A buffer of raw binary data, such as mesh data or a serialized blob.
+///
+/// The buffer owns its memory and frees it when dropped. A buffer that is a part of a game
+/// structure is owned by that structure, so it must only be accessed by reference. Reading it
+/// out by value, e.g. with [`ptr::read`], and letting Rust drop it frees memory that the game
+/// still uses.
+#[repr(transparent)]
+pub struct DataBuffer(red::DataBuffer);
+
+impl DataBuffer {
+    /// Returns the contents of the buffer.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        let raw = &self.0.buffer;
+        if raw.data.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(raw.data.cast(), raw.size as usize) }
+    }
+
+    /// Returns the contents of the buffer as a mutable slice.
+    #[inline]
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        let raw = &mut self.0.buffer;
+        if raw.data.is_null() {
+            return &mut [];
+        }
+        unsafe { slice::from_raw_parts_mut(raw.data.cast(), raw.size as usize) }
+    }
+
+    /// Returns the size of the buffer in bytes.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.0.buffer.size
+    }
+
+    /// Returns `true` if the buffer is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    fn allocator(&self) -> &IAllocator {
+        unsafe {
+            &*(&self.0.buffer.allocator as *const red::Memory::IAllocator).cast::<IAllocator>()
+        }
+    }
+}
+
+impl Default for DataBuffer {
+    #[inline]
+    fn default() -> Self {
+        Self::from(&[][..])
+    }
+}
+
+impl Clone for DataBuffer {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::from(self.as_bytes())
+    }
+}
+
+impl From<&[u8]> for DataBuffer {
+    /// Creates a buffer with a copy of the bytes allocated from the engine pool.
+    fn from(value: &[u8]) -> Self {
+        let size = u32::try_from(value.len()).expect("buffer size should fit in u32");
+        let allocator = IAllocator::container();
+        let data = if size == 0 {
+            ptr::null_mut()
+        } else {
+            let data = unsafe { allocator.alloc_aligned::<u8>(size, BUFFER_ALIGNMENT) };
+            assert!(!data.is_null(), "buffer allocation should not fail");
+            unsafe { data.copy_from_nonoverlapping(value.as_ptr(), value.len()) };
+            data
+        };
+        let buffer = red::RawBuffer {
+            data: data.cast(),
+            size,
+            alignment: BUFFER_ALIGNMENT,
+            // the allocator is stored by value, it's only a vtable pointer
+            allocator: unsafe {
+                ptr::read((allocator as *const IAllocator).cast::<red::Memory::IAllocator>())
+            },
+        };
+        Self(red::DataBuffer {
+            buffer,
+            ..Default::default()
+        })
+    }
+}
+
+impl From<Vec<u8>> for DataBuffer {
+    #[inline]
+    fn from(value: Vec<u8>) -> Self {
+        Self::from(value.as_slice())
+    }
+}
+
+impl AsRef<[u8]> for DataBuffer {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Debug for DataBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataBuffer")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Drop for DataBuffer {
+    #[inline]
+    fn drop(&mut self) {
+        let data = self.0.buffer.data;
+        if !data.is_null() {
+            unsafe { self.allocator().free(data) };
+        }
+    }
+}
+
+/// A reference-counted [`DataBuffer`].
+#[repr(transparent)]
+pub struct SharedDataBuffer(red::SharedDataBuffer);
+
+impl SharedDataBuffer {
+    /// Returns the underlying buffer, if any.
+    #[inline]
+    pub fn buffer(&self) -> Option<&DataBuffer> {
+        self.shared().get()
+    }
+
+    /// Returns the contents of the buffer. The slice is empty if there's no buffer.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buffer().map_or(&[], DataBuffer::as_bytes)
+    }
+
+    /// Returns the size of the buffer in bytes.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.buffer().map_or(0, DataBuffer::len)
+    }
+
+    /// Returns `true` if the buffer is missing or empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    fn from_shared(shared: SharedPtr<DataBuffer>) -> Self {
+        let mut this = Self(red::SharedDataBuffer::default());
+        unsafe {
+            ptr::write(
+                &mut this.0.buffer as *mut _ as *mut SharedPtr<DataBuffer>,
+                shared,
+            )
+        };
+        this
+    }
+
+    #[inline]
+    fn shared(&self) -> &SharedPtr<DataBuffer> {
+        unsafe { &*(&self.0.buffer as *const _ as *const SharedPtr<DataBuffer>) }
+    }
+}
+
+impl Default for SharedDataBuffer {
+    #[inline]
+    fn default() -> Self {
+        Self::from_shared(SharedPtr::null())
+    }
+}
+
+impl Clone for SharedDataBuffer {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::from_shared(self.shared().clone())
+    }
+}
+
+impl From<DataBuffer> for SharedDataBuffer {
+    #[inline]
+    fn from(value: DataBuffer) -> Self {
+        Self::from_shared(SharedPtr::new(value))
+    }
+}
+
+impl From<&[u8]> for SharedDataBuffer {
+    #[inline]
+    fn from(value: &[u8]) -> Self {
+        Self::from(DataBuffer::from(value))
+    }
+}
+
+impl From<Vec<u8>> for SharedDataBuffer {
+    #[inline]
+    fn from(value: Vec<u8>) -> Self {
+        Self::from(DataBuffer::from(value))
+    }
+}
+
+impl fmt::Debug for SharedDataBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedDataBuffer")
+            .field(&self.buffer())
+            .finish()
+    }
+}
+
+impl Drop for SharedDataBuffer {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(&mut self.0.buffer as *mut _ as *mut SharedPtr<DataBuffer>) };
+    }
+}
+
+/// A buffer whose contents are loaded on demand.
+///
+/// # Example
+/// ```rust
+/// use red4rs::types::DeferredDataBuffer;
+///
+/// fn read_bytes(buffer: &mut DeferredDataBuffer) -> Vec<u8> {
+///     if !buffer.is_loaded() {
+///         buffer.load().wait();
+///     }
+///     buffer.as_bytes().to_vec()
+/// }
+/// ```
+#[repr(transparent)]
+pub struct DeferredDataBuffer(red::DeferredDataBuffer);
+
+impl DeferredDataBuffer {
+    /// Schedules loading of the buffer contents. The returned handle can be used to wait
+    /// for the job to finish.
+    #[inline]
+    pub fn load(&mut self) -> JobHandle {
+        JobHandle::from_raw(unsafe { self.0.LoadAsync() })
+    }
+
+    /// Returns `true` if the buffer contents have been loaded.
+    #[inline]
+    pub fn is_loaded(&self) -> bool {
+        self.buffer().is_some()
+    }
+
+    /// Returns the loaded buffer, if any.
+    #[inline]
+    pub fn buffer(&self) -> Option<&DataBuffer> {
+        self.shared().buffer()
+    }
+
+    /// Returns the contents of the buffer. The slice is empty if it hasn't been loaded yet.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.shared().as_bytes()
+    }
+
+    /// Returns the size of the loaded buffer in bytes.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.shared().len()
+    }
+
+    /// Returns `true` if the buffer is not loaded or empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    fn shared(&self) -> &SharedDataBuffer {
+        unsafe { &*(&self.0.raw as *const red::SharedDataBuffer).cast::<SharedDataBuffer>() }
+    }
+}
+
+impl fmt::Debug for DeferredDataBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DeferredDataBuffer")
+            .field(&self.buffer())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataBuffer, SharedDataBuffer, BUFFER_ALIGNMENT};
+    use crate::types::allocator::mock;
+
+    #[test]
+    fn buffer_from_bytes() {
+        mock::install();
+        let buf = DataBuffer::from(&b"hello"[..]);
+        assert_eq!(buf.len(), 5);
+        assert_eq!(buf.as_bytes(), b"hello");
+        assert_eq!(
+            buf.as_bytes().as_ptr() as usize % BUFFER_ALIGNMENT as usize,
+            0
+        );
+
+        let mut buf = DataBuffer::from(vec![1, 2, 3]);
+        buf.as_bytes_mut()[1] = 5;
+        assert_eq!(buf.as_bytes(), &[1, 5, 3]);
+
+        let copy = buf.clone();
+        drop(buf);
+        assert_eq!(copy.as_bytes(), &[1, 5, 3]);
+    }
+
+    #[test]
+    fn empty_buffer() {
+        mock::install();
+        let buf = DataBuffer::default();
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_bytes(), &[]);
+        assert!(DataBuffer::from(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn shared_buffer() {
+        mock::install();
+        let buf = SharedDataBuffer::from(vec![1, 2, 3]);
+        let copy = buf.clone();
+        assert_eq!(buf.shared().strong_count(), 2);
+        drop(buf);
+        assert_eq!(copy.shared().strong_count(), 1);
+        assert_eq!(copy.as_bytes(), &[1, 2, 3]);
+        assert_eq!(copy.len(), 3);
+
+        let empty = SharedDataBuffer::default();
+        assert!(empty.buffer().is_none());
+        assert!(empty.is_empty());
+    }
+}
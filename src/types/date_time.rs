@@ -0,0 +1,328 @@
+use std::hash::Hash;
+use std::{fmt, mem};
+
+use thiserror::Error;
+
+use crate::raw::root::RED4ext as red;
+
+// the date is stored in the upper 32 bits and the time in the lower 32 bits, with the most
+// significant components first, so that the packed values are ordered chronologically
+const YEAR: Field = Field::new(52, 12);
+const MONTH: Field = Field::new(47, 5);
+const DAY: Field = Field::new(42, 5);
+const HOUR: Field = Field::new(22, 5);
+const MINUTE: Field = Field::new(16, 6);
+const SECOND: Field = Field::new(10, 6);
+const MILLISECOND: Field = Field::new(0, 10);
+
+/// A calendar date and time with millisecond precision, used for things like save-game
+/// timestamps.
+#[derive(Default, Clone, Copy)]
+#[repr(transparent)]
+pub struct DateTime(red::CDateTime);
+
+impl DateTime {
+    /// Creates a new date and time. The month and the day start at 1.
+    /// Returns an error if any of the components is out of range.
+    pub const fn new(
+        year: u32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+        millisecond: u32,
+    ) -> Result<Self, DateTimeError> {
+        if year > YEAR.max()
+            || month == 0
+            || month > 12
+            || day == 0
+            || day > 31
+            || hour > 23
+            || minute > 59
+            || second > 59
+            || millisecond > 999
+        {
+            return Err(DateTimeError::OutOfRange);
+        }
+        let bits = YEAR.encode(year)
+            | MONTH.encode(month - 1)
+            | DAY.encode(day - 1)
+            | HOUR.encode(hour)
+            | MINUTE.encode(minute)
+            | SECOND.encode(second)
+            | MILLISECOND.encode(millisecond);
+        Ok(Self::from_bits(bits))
+    }
+
+    #[inline]
+    pub const fn year(self) -> u32 {
+        YEAR.decode(self.bits())
+    }
+
+    /// Returns the month, starting at 1.
+    #[inline]
+    pub const fn month(self) -> u32 {
+        MONTH.decode(self.bits()) + 1
+    }
+
+    /// Returns the day of the month, starting at 1.
+    #[inline]
+    pub const fn day(self) -> u32 {
+        DAY.decode(self.bits()) + 1
+    }
+
+    #[inline]
+    pub const fn hour(self) -> u32 {
+        HOUR.decode(self.bits())
+    }
+
+    #[inline]
+    pub const fn minute(self) -> u32 {
+        MINUTE.decode(self.bits())
+    }
+
+    #[inline]
+    pub const fn second(self) -> u32 {
+        SECOND.decode(self.bits())
+    }
+
+    #[inline]
+    pub const fn millisecond(self) -> u32 {
+        MILLISECOND.decode(self.bits())
+    }
+
+    #[inline]
+    const fn from_bits(bits: u64) -> Self {
+        Self(unsafe { mem::transmute::<u64, red::CDateTime>(bits) })
+    }
+
+    #[inline]
+    const fn bits(self) -> u64 {
+        unsafe { mem::transmute::<red::CDateTime, u64>(self.0) }
+    }
+}
+
+impl PartialEq for DateTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits() == other.bits()
+    }
+}
+
+impl Eq for DateTime {}
+
+impl PartialOrd for DateTime {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DateTime {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.bits().cmp(&other.bits())
+    }
+}
+
+impl Hash for DateTime {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.bits().hash(state);
+    }
+}
+
+impl From<u64> for DateTime {
+    fn from(bits: u64) -> Self {
+        Self::from_bits(bits)
+    }
+}
+
+impl From<DateTime> for u64 {
+    fn from(value: DateTime) -> Self {
+        value.bits()
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+            self.year(),
+            self.month(),
+            self.day(),
+            self.hour(),
+            self.minute(),
+            self.second(),
+            self.millisecond()
+        )
+    }
+}
+
+impl fmt::Debug for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DateTime({self})")
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DateTimeError {
+    #[error("date or time component is out of range")]
+    OutOfRange,
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<DateTime> for chrono::NaiveDateTime {
+    type Error = DateTimeError;
+
+    fn try_from(value: DateTime) -> Result<Self, Self::Error> {
+        chrono::NaiveDate::from_ymd_opt(value.year() as i32, value.month(), value.day())
+            .and_then(|date| {
+                date.and_hms_milli_opt(
+                    value.hour(),
+                    value.minute(),
+                    value.second(),
+                    value.millisecond(),
+                )
+            })
+            .ok_or(DateTimeError::OutOfRange)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<chrono::NaiveDateTime> for DateTime {
+    type Error = DateTimeError;
+
+    fn try_from(value: chrono::NaiveDateTime) -> Result<Self, Self::Error> {
+        use chrono::{Datelike, Timelike};
+
+        let year = u32::try_from(value.year()).map_err(|_| DateTimeError::OutOfRange)?;
+        Self::new(
+            year,
+            value.month(),
+            value.day(),
+            value.hour(),
+            value.minute(),
+            value.second(),
+            // leap seconds are clamped
+            (value.nanosecond() / 1_000_000).min(999),
+        )
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<DateTime> for time::PrimitiveDateTime {
+    type Error = time::error::ComponentRange;
+
+    fn try_from(value: DateTime) -> Result<Self, Self::Error> {
+        let month = time::Month::try_from(value.month() as u8)?;
+        let date = time::Date::from_calendar_date(value.year() as i32, month, value.day() as u8)?;
+        let time = time::Time::from_hms_milli(
+            value.hour() as u8,
+            value.minute() as u8,
+            value.second() as u8,
+            value.millisecond() as u16,
+        )?;
+        Ok(Self::new(date, time))
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<time::PrimitiveDateTime> for DateTime {
+    type Error = DateTimeError;
+
+    fn try_from(value: time::PrimitiveDateTime) -> Result<Self, Self::Error> {
+        let year = u32::try_from(value.year()).map_err(|_| DateTimeError::OutOfRange)?;
+        Self::new(
+            year,
+            u8::from(value.month()) as u32,
+            value.day() as u32,
+            value.hour() as u32,
+            value.minute() as u32,
+            value.second() as u32,
+            value.millisecond() as u32,
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Field {
+    offset: u32,
+    width: u32,
+}
+
+impl Field {
+    const fn new(offset: u32, width: u32) -> Self {
+        Self { offset, width }
+    }
+
+    const fn max(self) -> u32 {
+        (1 << self.width) - 1
+    }
+
+    const fn encode(self, value: u32) -> u64 {
+        (value as u64 & self.max() as u64) << self.offset
+    }
+
+    const fn decode(self, bits: u64) -> u32 {
+        (bits >> self.offset) as u32 & self.max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DateTime;
+
+    #[test]
+    fn packing() {
+        let dt = DateTime::new(2077, 4, 16, 1, 24, 7, 500).unwrap();
+        let date = (2077 << 20) | (3 << 15) | (15 << 10);
+        let time = (1 << 22) | (24 << 16) | (7 << 10) | 500;
+        assert_eq!(u64::from(dt), (date << 32) | time);
+
+        assert_eq!(dt.year(), 2077);
+        assert_eq!(dt.month(), 4);
+        assert_eq!(dt.day(), 16);
+        assert_eq!(dt.hour(), 1);
+        assert_eq!(dt.minute(), 24);
+        assert_eq!(dt.second(), 7);
+        assert_eq!(dt.millisecond(), 500);
+        assert_eq!(dt.to_string(), "2077-04-16 01:24:07.500");
+    }
+
+    #[test]
+    fn validation_and_ordering() {
+        assert!(DateTime::new(2077, 13, 1, 0, 0, 0, 0).is_err());
+        assert!(DateTime::new(2077, 1, 0, 0, 0, 0, 0).is_err());
+        assert!(DateTime::new(2077, 1, 1, 24, 0, 0, 0).is_err());
+        assert!(DateTime::new(4096, 1, 1, 0, 0, 0, 0).is_err());
+
+        let earlier = DateTime::new(2077, 4, 16, 23, 59, 59, 999).unwrap();
+        let later = DateTime::new(2077, 4, 17, 0, 0, 0, 0).unwrap();
+        assert!(earlier < later);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn chrono() {
+        use chrono::NaiveDateTime;
+
+        let dt = DateTime::new(2077, 4, 16, 1, 24, 7, 500).unwrap();
+        let naive = NaiveDateTime::try_from(dt).unwrap();
+        assert_eq!(naive.to_string(), "2077-04-16 01:24:07.500");
+        assert_eq!(DateTime::try_from(naive).unwrap(), dt);
+
+        let invalid = DateTime::new(2077, 2, 31, 0, 0, 0, 0).unwrap();
+        assert!(NaiveDateTime::try_from(invalid).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn time() {
+        use time::PrimitiveDateTime;
+
+        let dt = DateTime::new(2077, 4, 16, 1, 24, 7, 500).unwrap();
+        let primitive = PrimitiveDateTime::try_from(dt).unwrap();
+        assert_eq!(primitive.year(), 2077);
+        assert_eq!(primitive.millisecond(), 500);
+        assert_eq!(DateTime::try_from(primitive).unwrap(), dt);
+    }
+}
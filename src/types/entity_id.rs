@@ -1,12 +1,67 @@
+use std::fmt;
 use std::hash::Hash;
 
+use super::NodeRef;
 use crate::raw::root::RED4ext as red;
 
-#[derive(Debug, Clone, Copy)]
+/// An identifier of an entity. Static entities placed in the world are identified by the
+/// hash of their node path, while dynamic entities spawned at runtime get an id allocated by
+/// the engine.
+///
+/// # Example
+/// ```rust
+/// use red4rs::types::EntityId;
+///
+/// const NPC: EntityId = EntityId::from_node_path("$/03_night_city/district/npc");
+///
+/// fn spawned_id() -> EntityId {
+///     EntityId::new_dynamic()
+/// }
+/// ```
+#[derive(Clone, Copy)]
 #[repr(transparent)]
 pub struct EntityId(red::ent::EntityID);
 
 impl EntityId {
+    /// Creates the id of a static entity from the path of its world node.
+    #[inline]
+    pub const fn from_node_path(path: &str) -> Self {
+        Self::from_node_ref(NodeRef::new(path))
+    }
+
+    /// Creates the id of a static entity from a reference to its world node.
+    #[inline]
+    pub const fn from_node_ref(node: NodeRef) -> Self {
+        Self(red::ent::EntityID { hash: node.hash() })
+    }
+
+    /// Allocates a new dynamic id through the engine. Every call returns a different id.
+    pub fn new_dynamic() -> Self {
+        let next_hash = unsafe {
+            crate::fn_from_hash!(EntityID_GetNextDynamicHash, unsafe extern "C" fn() -> u64)
+        };
+        Self(red::ent::EntityID {
+            hash: unsafe { next_hash() },
+        })
+    }
+
+    #[inline]
+    pub const fn hash(self) -> u64 {
+        self.0.hash
+    }
+
+    /// Returns the kind of the entity the id refers to.
+    #[inline]
+    pub const fn kind(self) -> EntityKind {
+        if self.is_static() {
+            EntityKind::Static
+        } else if self.is_dynamic() {
+            EntityKind::Dynamic
+        } else {
+            EntityKind::Undefined
+        }
+    }
+
     #[inline]
     pub const fn is_defined(self) -> bool {
         self.0.hash != 0
@@ -66,6 +121,26 @@ impl Default for EntityId {
     }
 }
 
+/// Prints the kind of the entity followed by the hash, e.g. `static:0x445666BF489EC042`.
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{:#018X}", self.kind(), self.0.hash)
+    }
+}
+
+impl fmt::Debug for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EntityId({:#018X})", self.0.hash)
+    }
+}
+
+impl From<NodeRef> for EntityId {
+    #[inline]
+    fn from(node: NodeRef) -> Self {
+        Self::from_node_ref(node)
+    }
+}
+
 impl From<u64> for EntityId {
     fn from(hash: u64) -> Self {
         Self(red::ent::EntityID { hash })
@@ -77,3 +152,47 @@ impl From<EntityId> for u64 {
         hash
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntityKind {
+    Undefined,
+    Static,
+    Dynamic,
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undefined => f.write_str("undefined"),
+            Self::Static => f.write_str("static"),
+            Self::Dynamic => f.write_str("dynamic"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EntityId, EntityKind};
+    use crate::types::NodeRef;
+
+    #[test]
+    fn static_ids() {
+        let id = EntityId::from_node_path("$/03_night_city");
+        assert_eq!(id.hash(), 0x4456_66BF_489E_C042);
+        assert_eq!(id, EntityId::from(NodeRef::new("03_night_city/")));
+        assert_eq!(id.kind(), EntityKind::Static);
+        assert_eq!(id.to_string(), "static:0x445666BF489EC042");
+        assert_eq!(format!("{id:?}"), "EntityId(0x445666BF489EC042)");
+    }
+
+    #[test]
+    fn kinds() {
+        assert_eq!(EntityId::default().kind(), EntityKind::Undefined);
+        assert_eq!(
+            EntityId::default().to_string(),
+            "undefined:0x0000000000000000"
+        );
+        assert_eq!(EntityId::from(42).kind(), EntityKind::Dynamic);
+        assert_eq!(EntityId::from(42).to_string(), "dynamic:0x000000000000002A");
+    }
+}
@@ -0,0 +1,184 @@
+use std::hash::Hash;
+use std::str::FromStr;
+use std::{fmt, mem};
+
+use thiserror::Error;
+
+use crate::raw::root::RED4ext as red;
+
+/// A globally unique identifier. It's made of four 32-bit parts, which are formatted as
+/// `XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX` with the second and third part split in half.
+#[derive(Default, Clone, Copy)]
+#[repr(transparent)]
+pub struct Guid(red::CGUID);
+
+impl Guid {
+    /// Creates a new GUID from its parts.
+    #[inline]
+    pub const fn from_parts(parts: [u32; 4]) -> Self {
+        Self(unsafe { mem::transmute::<[u32; 4], red::CGUID>(parts) })
+    }
+
+    /// Generates a new random (version 4) GUID.
+    pub fn random() -> Self {
+        let (hi, lo) = (crate::random_u64(), crate::random_u64());
+        Self::from_parts([
+            (hi >> 32) as u32,
+            // the version and variant bits are set as in RFC 4122
+            (hi as u32 & 0xFFFF_0FFF) | 0x4000,
+            ((lo >> 32) as u32 & 0x3FFF_FFFF) | 0x8000_0000,
+            lo as u32,
+        ])
+    }
+
+    /// Returns the parts of the GUID.
+    #[inline]
+    pub const fn parts(self) -> [u32; 4] {
+        unsafe { mem::transmute::<red::CGUID, [u32; 4]>(self.0) }
+    }
+
+    /// Returns `true` if any part of the GUID is non-zero.
+    #[inline]
+    pub const fn is_defined(self) -> bool {
+        let [a, b, c, d] = self.parts();
+        a | b | c | d != 0
+    }
+}
+
+impl PartialEq for Guid {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.parts() == other.parts()
+    }
+}
+
+impl Eq for Guid {}
+
+impl PartialOrd for Guid {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Guid {
+    #[inline]
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.parts().cmp(&other.parts())
+    }
+}
+
+impl Hash for Guid {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.parts().hash(state);
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.parts();
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-{:04X}-{:04X}{:08X}",
+            a,
+            b >> 16,
+            b & 0xFFFF,
+            c >> 16,
+            c & 0xFFFF,
+            d
+        )
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Guid({self})")
+    }
+}
+
+impl FromStr for Guid {
+    type Err = GuidParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .unwrap_or(s);
+        let groups: Vec<&str> = s.split('-').collect();
+        let [a, b1, b2, c1, rest] = groups[..] else {
+            return Err(GuidParseError::InvalidFormat);
+        };
+        if [a, b1, b2, c1, rest].map(str::len) != [8, 4, 4, 4, 12] {
+            return Err(GuidParseError::InvalidFormat);
+        }
+        // the digits are checked before splitting, a multi-byte character could straddle the
+        // split point, and from_str_radix would also accept a sign
+        if !groups
+            .iter()
+            .all(|g| g.bytes().all(|b| b.is_ascii_hexdigit()))
+        {
+            return Err(GuidParseError::InvalidDigit);
+        }
+        let (c2, d) = rest.split_at(4);
+        let parse =
+            |str: &str| u32::from_str_radix(str, 16).map_err(|_| GuidParseError::InvalidDigit);
+        Ok(Self::from_parts([
+            parse(a)?,
+            parse(b1)? << 16 | parse(b2)?,
+            parse(c1)? << 16 | parse(c2)?,
+            parse(d)?,
+        ]))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GuidParseError {
+    #[error("GUID should be in the XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX format")]
+    InvalidFormat,
+    #[error("GUID should only contain hexadecimal digits")]
+    InvalidDigit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Guid;
+
+    #[test]
+    fn round_trip() {
+        let guid = Guid::from_parts([0x0123_4567, 0x89AB_CDEF, 0xFEDC_BA98, 0x7654_3210]);
+        let str = guid.to_string();
+        assert_eq!(str, "01234567-89AB-CDEF-FEDC-BA9876543210");
+        assert_eq!(str.parse::<Guid>().unwrap(), guid);
+        assert_eq!(
+            "{01234567-89ab-cdef-fedc-ba9876543210}"
+                .parse::<Guid>()
+                .unwrap(),
+            guid
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("01234567-89AB-CDEF-FEDC".parse::<Guid>().is_err());
+        assert!("01234567-89AB-CDEF-FEDCB-A9876543210"
+            .parse::<Guid>()
+            .is_err());
+        assert!("0123456G-89AB-CDEF-FEDC-BA9876543210"
+            .parse::<Guid>()
+            .is_err());
+        assert!("+1234567-89AB-CDEF-FEDC-BA9876543210"
+            .parse::<Guid>()
+            .is_err());
+        assert!("01234567-89AB-CDEF-FEDC-€€€€".parse::<Guid>().is_err());
+    }
+
+    #[test]
+    fn random() {
+        let (a, b) = (Guid::random(), Guid::random());
+        assert_ne!(a, b);
+        assert!(a.is_defined());
+        assert_eq!(a.parts()[1] >> 12 & 0xF, 4);
+        assert_eq!(a.parts()[2] >> 30, 0b10);
+    }
+}
@@ -1,100 +1,259 @@
 use std::iter::FusedIterator;
-use std::{mem, ptr, slice};
+use std::marker::PhantomData;
+use std::{fmt, mem, ptr, slice};
 
-use super::{CName, IAllocator};
+use super::{CName, EntityId, IAllocator, RedString, TweakDbId};
 use crate::raw::root::RED4ext as red;
 
 const INVALID_INDEX: u32 = u32::MAX;
 
-#[derive(Debug)]
+/// A hash map compatible with the one used by the game.
 #[repr(transparent)]
 pub struct RedHashMap<K, V>(red::HashMap<K, V>);
 
 impl<K, V> RedHashMap<K, V> {
+    /// Creates a new empty map that allocates from the engine pool.
+    #[inline]
+    pub fn new() -> Self {
+        let allocator = IAllocator::container();
+        Self(red::HashMap {
+            allocator: allocator as *const IAllocator as *mut red::Memory::IAllocator,
+            ..Default::default()
+        })
+    }
+
+    /// Creates a new empty map with space for at least `capacity` entries.
+    #[inline]
+    pub fn with_capacity(capacity: u32) -> Self {
+        let mut this = Self::new();
+        if capacity > 0 {
+            this.realloc(capacity);
+        }
+        this
+    }
+
+    /// Returns a reference to the value corresponding to the key.
     #[inline]
     pub fn get(&self, key: &K) -> Option<&V>
     where
         K: Hash + PartialEq,
     {
-        self.get_by_hash(key.hash())
+        let index = self.find(key)?;
+        Some(&self.nodes()[index as usize].value)
     }
 
+    /// Returns a mutable reference to the value corresponding to the key.
     #[inline]
     pub fn get_mut(&mut self, key: &K) -> Option<&mut V>
     where
         K: Hash + PartialEq,
     {
-        self.get_by_hash_mut(key.hash())
+        let index = self.find(key)?;
+        Some(&mut self.nodes_mut()[index as usize].value)
     }
 
-    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    /// Returns `true` if the map contains a value for the key.
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool
     where
         K: Hash + PartialEq,
     {
-        let hash = key.hash();
+        self.find(key).is_some()
+    }
 
-        if self.size() > 0 {
-            if let Some(slot) = self.get_by_hash_mut(hash) {
-                return Some(mem::replace(slot, value));
-            }
+    /// Inserts a key-value pair into the map. If the key was already present, the value is
+    /// replaced and the old one is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    where
+        K: Hash + PartialEq,
+    {
+        if let Some(index) = self.find(&key) {
+            return Some(mem::replace(
+                &mut self.nodes_mut()[index as usize].value,
+                value,
+            ));
         }
-        if self.size() + 1 > self.capacity() {
-            self.realloc((self.capacity() + self.capacity() / 2).max(4));
+        self.insert_new(key.hash(), key, value);
+        None
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    #[inline]
+    pub fn remove(&mut self, key: &K) -> Option<V>
+    where
+        K: Hash + PartialEq,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes a key from the map, returning the stored key and value if it was present.
+    #[inline]
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)>
+    where
+        K: Hash + PartialEq,
+    {
+        self.remove_node(key.hash(), |_, node_key| node_key == key)
+    }
+
+    /// Gets the entry corresponding to the key for in-place manipulation.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V>
+    where
+        K: Hash + PartialEq,
+    {
+        match self.find(&key) {
+            Some(index) => Entry::Occupied(OccupiedEntry {
+                hash: self.nodes()[index as usize].hashedKey,
+                map: self,
+                index,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                hash: key.hash(),
+                map: self,
+                key,
+            }),
         }
-        let (node_list, index_table) = self.split_mut();
-        Self::push_node(node_list, index_table, hash, key, value);
-        self.0.size += 1;
+    }
 
-        None
+    /// Removes all entries from the map, keeping the allocated memory.
+    pub fn clear(&mut self) {
+        self.drop_entries();
+        self.indexes_mut().fill(INVALID_INDEX);
+        self.0.nodeList.size = 0;
+        self.0.nodeList.nextIdx = 0;
+        self.0.size = 0;
     }
 
+    /// Returns an iterator over the entries of the map.
     #[inline]
     pub fn iter(&self) -> Iter<'_, K, V> {
         self.into_iter()
     }
 
+    /// Returns an iterator over the entries of the map with mutable references to the values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.into_iter()
+    }
+
+    /// Returns an iterator over the keys of the map.
+    #[inline]
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.iter())
+    }
+
+    /// Returns an iterator over the values of the map.
+    #[inline]
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values(self.iter())
+    }
+
+    /// Returns an iterator over mutable references to the values of the map.
+    #[inline]
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut(self.iter_mut())
+    }
+
+    /// Returns the number of entries in the map.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.0.size
+    }
+
+    /// Returns `true` if the map contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of entries in the map.
     #[inline]
     pub fn size(&self) -> u32 {
         self.0.size
     }
 
+    /// Returns the number of entries the map can hold without reallocating.
     #[inline]
     pub fn capacity(&self) -> u32 {
         self.0.capacity
     }
 
-    fn get_by_hash(&self, hash: u32) -> Option<&V> {
+    fn find(&self, key: &K) -> Option<u32>
+    where
+        K: Hash + PartialEq,
+    {
+        let hash = key.hash();
         let mut cur = *self
             .indexes()
             .get((hash.checked_rem(self.capacity()))? as usize)?;
         while cur != INVALID_INDEX {
             let node = self.nodes().get(cur as usize)?;
-            if node.hashedKey == hash {
-                return Some(&node.value);
+            if node.hashedKey == hash && node.key == *key {
+                return Some(cur);
             }
             cur = node.next;
         }
         None
     }
 
-    fn get_by_hash_mut(&mut self, hash: u32) -> Option<&mut V> {
-        let mut cur = *self
-            .indexes()
-            .get((hash.checked_rem(self.capacity()))? as usize)?;
-        while cur != INVALID_INDEX {
-            let node = self.nodes_mut().get(cur as usize)?;
-            if node.hashedKey == hash {
-                return Some(&mut self.nodes_mut().get_mut(cur as usize)?.value);
+    fn insert_new(&mut self, hash: u32, key: K, value: V) -> u32 {
+        if self.size() + 1 > self.capacity() {
+            self.realloc((self.capacity() + self.capacity() / 2).max(4));
+        }
+        let (node_list, index_table) = self.split_mut();
+        let index = Self::push_node(node_list, index_table, hash, key, value);
+        self.0.size += 1;
+        index
+    }
+
+    fn remove_node<F>(&mut self, hash: u32, mut pred: F) -> Option<(K, V)>
+    where
+        F: FnMut(u32, &K) -> bool,
+    {
+        let bucket = hash.checked_rem(self.capacity())?;
+        let (node_list, index_table) = self.split_mut();
+        let mut link: *mut u32 = &mut index_table[bucket as usize];
+        unsafe {
+            while *link != INVALID_INDEX {
+                let index = *link;
+                let node = node_list.nodes.add(index as usize);
+                if (*node).hashedKey == hash && pred(index, &(*node).key) {
+                    *link = (*node).next;
+                    let entry = (ptr::read(&(*node).key), ptr::read(&(*node).value));
+                    // the node becomes the head of the free list
+                    (*node).next = node_list.nextIdx;
+                    node_list.nextIdx = index;
+                    self.0.size -= 1;
+                    return Some(entry);
+                }
+                link = &mut (*node).next;
             }
-            cur = node.next;
         }
         None
     }
 
+    fn drop_entries(&mut self) {
+        let nodes = self.0.nodeList.nodes;
+        for &head in self.indexes() {
+            let mut cur = head;
+            while cur != INVALID_INDEX {
+                unsafe {
+                    let node = nodes.add(cur as usize);
+                    cur = (*node).next;
+                    ptr::drop_in_place(&mut (*node).key);
+                    ptr::drop_in_place(&mut (*node).value);
+                }
+            }
+        }
+    }
+
     fn realloc(&mut self, new_capacity: u32) {
         let new_cap_bytes = new_capacity as usize
             * (mem::size_of::<red::HashMap_Node<K, V>>() + mem::size_of::<u32>());
-        let mem = unsafe { self.allocator().alloc_aligned(new_cap_bytes as _, 8) };
+        let align = mem::align_of::<red::HashMap_Node<K, V>>().max(8);
+        let mem = unsafe {
+            self.allocator()
+                .alloc_aligned(new_cap_bytes as _, align as _)
+        };
 
         let mut node_list = red::HashMap_NodeList {
             nodes: mem,
@@ -141,7 +300,7 @@ impl<K, V> RedHashMap<K, V> {
         hash: u32,
         key: K,
         value: V,
-    ) {
+    ) -> u32 {
         let node = Self::next_free_node(node_list).unwrap();
         let next = &mut index_table[hash as usize % index_table.len()];
         unsafe {
@@ -150,6 +309,7 @@ impl<K, V> RedHashMap<K, V> {
             ptr::write(&mut (*node).value, value);
             (*node).next = *next;
             *next = node.offset_from(node_list.nodes) as _;
+            *next
         }
     }
 
@@ -161,12 +321,12 @@ impl<K, V> RedHashMap<K, V> {
         }
         if nl.nextIdx == nl.size {
             let node = unsafe { nl.nodes.add(nl.size as _) };
-            if nl.size + 1 < nl.capacity {
-                nl.size += 1;
-                nl.nextIdx += 1;
+            nl.size += 1;
+            nl.nextIdx = if nl.size < nl.capacity {
+                nl.size
             } else {
-                nl.nextIdx = INVALID_INDEX;
-            }
+                INVALID_INDEX
+            };
             return Some(node);
         }
         let node = unsafe { nl.nodes.add(nl.nextIdx as _) };
@@ -193,6 +353,11 @@ impl<K, V> RedHashMap<K, V> {
             .unwrap_or_default()
     }
 
+    #[inline]
+    fn indexes_mut(&mut self) -> &mut [u32] {
+        self.split_mut().1
+    }
+
     #[inline]
     fn nodes(&self) -> &[red::HashMap_Node<K, V>] {
         (self.capacity() > 0)
@@ -217,6 +382,47 @@ impl<K, V> RedHashMap<K, V> {
     }
 }
 
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for RedHashMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> Default for RedHashMap<K, V> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + PartialEq, V> FromIterator<(K, V)> for RedHashMap<K, V> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Hash + PartialEq, V> Extend<(K, V)> for RedHashMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V> Drop for RedHashMap<K, V> {
+    fn drop(&mut self) {
+        if self.capacity() == 0 {
+            return;
+        }
+        self.drop_entries();
+        // the index table is a part of the same allocation as the nodes
+        unsafe { self.allocator().free(self.0.nodeList.nodes) };
+    }
+}
+
 impl<'a, K, V> IntoIterator for &'a RedHashMap<K, V> {
     type IntoIter = Iter<'a, K, V>;
     type Item = (&'a K, &'a V);
@@ -231,6 +437,156 @@ impl<'a, K, V> IntoIterator for &'a RedHashMap<K, V> {
     }
 }
 
+impl<'a, K, V> IntoIterator for &'a mut RedHashMap<K, V> {
+    type IntoIter = IterMut<'a, K, V>;
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        IterMut {
+            current_index: INVALID_INDEX,
+            indexes: self.indexes(),
+            nodes: self.0.nodeList.nodes,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// A view into a single entry of a [`RedHashMap`], which may be either occupied or vacant.
+#[derive(Debug)]
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    /// Returns a reference to the key of the entry.
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Self::Occupied(entry) => entry.key(),
+            Self::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting `default` if empty.
+    #[inline]
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default` if empty.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Self::Occupied(entry) => entry.into_mut(),
+            Self::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty.
+    #[inline]
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Modifies the value of an occupied entry.
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Self::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+/// An occupied entry of a [`RedHashMap`].
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, K, V> {
+    map: &'a mut RedHashMap<K, V>,
+    index: u32,
+    hash: u32,
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    /// Returns a reference to the key of the entry.
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.map.nodes()[self.index as usize].key
+    }
+
+    /// Returns a reference to the value of the entry.
+    #[inline]
+    pub fn get(&self) -> &V {
+        &self.map.nodes()[self.index as usize].value
+    }
+
+    /// Returns a mutable reference to the value of the entry.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.nodes_mut()[self.index as usize].value
+    }
+
+    /// Converts the entry into a mutable reference to its value bound to the map.
+    #[inline]
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.nodes_mut()[self.index as usize].value
+    }
+
+    /// Replaces the value of the entry, returning the old one.
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the entry from the map, returning its value.
+    #[inline]
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Removes the entry from the map, returning its key and value.
+    #[inline]
+    pub fn remove_entry(self) -> (K, V) {
+        let index = self.index;
+        self.map
+            .remove_node(self.hash, |cur, _| cur == index)
+            .expect("occupied entry should be present")
+    }
+}
+
+/// A vacant entry of a [`RedHashMap`].
+#[derive(Debug)]
+pub struct VacantEntry<'a, K, V> {
+    map: &'a mut RedHashMap<K, V>,
+    key: K,
+    hash: u32,
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    /// Returns a reference to the key that would be used when inserting a value.
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes ownership of the key.
+    #[inline]
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts a value into the entry, returning a mutable reference to it.
+    #[inline]
+    pub fn insert(self, value: V) -> &'a mut V {
+        let index = self.map.insert_new(self.hash, self.key, value);
+        &mut self.map.nodes_mut()[index as usize].value
+    }
+}
+
 #[derive(Debug)]
 pub struct Iter<'a, K, V> {
     current_index: u32,
@@ -257,6 +613,79 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
 
 impl<K, V> FusedIterator for Iter<'_, K, V> {}
 
+#[derive(Debug)]
+pub struct IterMut<'a, K, V> {
+    current_index: u32,
+    indexes: &'a [u32],
+    nodes: *mut red::HashMap_Node<K, V>,
+    phantom: PhantomData<&'a mut red::HashMap_Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.current_index != INVALID_INDEX {
+                // every node is visited only once, so the mutable references are unique
+                let node = unsafe { &mut *self.nodes.add(self.current_index as usize) };
+                self.current_index = node.next;
+                return Some((&node.key, &mut node.value));
+            }
+
+            let (index, rem) = self.indexes.split_first()?;
+            self.current_index = *index;
+            self.indexes = rem;
+        }
+    }
+}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+#[derive(Debug)]
+pub struct Keys<'a, K, V>(Iter<'a, K, V>);
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, _)| key)
+    }
+}
+
+impl<K, V> FusedIterator for Keys<'_, K, V> {}
+
+#[derive(Debug)]
+pub struct Values<'a, K, V>(Iter<'a, K, V>);
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, value)| value)
+    }
+}
+
+impl<K, V> FusedIterator for Values<'_, K, V> {}
+
+#[derive(Debug)]
+pub struct ValuesMut<'a, K, V>(IterMut<'a, K, V>);
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, value)| value)
+    }
+}
+
+impl<K, V> FusedIterator for ValuesMut<'_, K, V> {}
+
+/// A trait for types that can be used as keys of a [`RedHashMap`]. The hashes match the ones
+/// computed by the game for the same keys.
 pub trait Hash {
     fn hash(&self) -> u32;
 }
@@ -264,14 +693,170 @@ pub trait Hash {
 impl Hash for CName {
     #[inline]
     fn hash(&self) -> u32 {
-        let hash = u64::from(*self);
-        hash as u32 ^ (hash >> 32) as u32
+        fold_u64(u64::from(*self))
+    }
+}
+
+impl Hash for TweakDbId {
+    #[inline]
+    fn hash(&self) -> u32 {
+        fold_u64(u64::from(*self))
+    }
+}
+
+impl Hash for EntityId {
+    #[inline]
+    fn hash(&self) -> u32 {
+        fold_u64(u64::from(*self))
+    }
+}
+
+impl Hash for RedString {
+    #[inline]
+    fn hash(&self) -> u32 {
+        crate::fnv1a32_bytes(self.as_bytes())
     }
 }
 
-impl Hash for u32 {
+macro_rules! impl_int_hash {
+    ($($ty:ty),*) => {
+        $(
+            impl Hash for $ty {
+                #[inline]
+                fn hash(&self) -> u32 {
+                    *self as u32
+                }
+            }
+        )*
+    };
+}
+
+impl_int_hash!(u8, u16, u32, i8, i16, i32);
+
+impl Hash for u64 {
     #[inline]
     fn hash(&self) -> u32 {
-        *self
+        fold_u64(*self)
+    }
+}
+
+impl Hash for i64 {
+    #[inline]
+    fn hash(&self) -> u32 {
+        fold_u64(*self as u64)
+    }
+}
+
+#[inline]
+fn fold_u64(val: u64) -> u32 {
+    val as u32 ^ (val >> 32) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::{Entry, RedHashMap};
+    use crate::types::allocator::mock;
+
+    #[test]
+    fn insert_and_get() {
+        mock::install();
+        let mut map = RedHashMap::new();
+        for i in 0..100u32 {
+            assert_eq!(map.insert(i, i * 2), None);
+        }
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.insert(7, 0), Some(14));
+        assert_eq!(map.get(&7), Some(&0));
+        assert_eq!(map.get(&99), Some(&198));
+        assert_eq!(map.get(&100), None);
+        assert!(map.contains_key(&42));
+
+        *map.get_mut(&42).unwrap() += 1;
+        assert_eq!(map.get(&42), Some(&85));
+    }
+
+    #[test]
+    fn colliding_keys() {
+        mock::install();
+        // both keys fold into the same 32-bit hash
+        let (a, b) = (1u64, 1u64 << 32);
+        let mut map: RedHashMap<u64, &str> = [(a, "a"), (b, "b")].into_iter().collect();
+        assert_eq!(map.get(&a), Some(&"a"));
+        assert_eq!(map.get(&b), Some(&"b"));
+        assert_eq!(map.remove(&a), Some("a"));
+        assert_eq!(map.get(&b), Some(&"b"));
+    }
+
+    #[test]
+    fn remove_and_reuse() {
+        mock::install();
+        let mut map: RedHashMap<u32, u32> = (0..10).map(|i| (i, i)).collect();
+        let capacity = map.capacity();
+        for i in (0..10).step_by(2) {
+            assert_eq!(map.remove(&i), Some(i));
+        }
+        assert_eq!(map.remove(&0), None);
+        assert_eq!(map.len(), 5);
+
+        for i in 10..15 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.capacity(), capacity);
+        let mut keys: Vec<_> = map.keys().copied().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, [1, 3, 5, 7, 9, 10, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn iterators() {
+        mock::install();
+        let mut map: RedHashMap<i32, i32> = (1..=4).map(|i| (i, i)).collect();
+        for (_, value) in map.iter_mut() {
+            *value *= 10;
+        }
+        for value in map.values_mut() {
+            *value += 1;
+        }
+        assert_eq!(map.values().sum::<i32>(), 104);
+        assert_eq!(map.iter().map(|(k, _)| k).sum::<i32>(), 10);
+    }
+
+    #[test]
+    fn entry_api() {
+        mock::install();
+        let mut map = RedHashMap::new();
+        *map.entry(1u32).or_insert(0) += 1;
+        *map.entry(1u32).or_insert(0) += 1;
+        map.entry(2).and_modify(|v| *v = 100).or_default();
+        assert_eq!(map.get(&1), Some(&2));
+        assert_eq!(map.get(&2), Some(&0));
+
+        match map.entry(1) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 2),
+            Entry::Vacant(_) => panic!("entry should be occupied"),
+        }
+        assert!(!map.contains_key(&1));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn clear_and_drop() {
+        mock::install();
+        let rc = Rc::new(());
+        let mut map: RedHashMap<u32, _> = (0..8).map(|i| (i, rc.clone())).collect();
+        map.remove(&3);
+        assert_eq!(Rc::strong_count(&rc), 8);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        map.insert(1, rc.clone());
+        map.insert(2, rc.clone());
+        assert_eq!(map.get(&2).map(Rc::strong_count), Some(3));
+        drop(map);
+        assert_eq!(Rc::strong_count(&rc), 1);
     }
 }
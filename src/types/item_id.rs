@@ -1,4 +1,5 @@
 use std::fmt;
+use std::hash::Hash;
 
 use super::TweakDbId;
 use crate::raw::root::RED4ext as red;
@@ -10,15 +11,45 @@ const DEFAULT_ITEM_ID_RNG_SEED: u32 = 2;
 pub struct ItemId(red::ItemID);
 
 impl ItemId {
+    /// Creates a new item id with the default seed and no unique counter.
+    /// Use [`ItemId::builder`] to control the other fields.
     #[inline]
     pub const fn new_from(id: TweakDbId) -> Self {
-        Self(red::ItemID {
-            tdbid: id.to_inner(),
-            rngSeed: DEFAULT_ITEM_ID_RNG_SEED,
-            uniqueCounter: 0,
-            structure: GamedataItemStructure::BlueprintStackable as u8,
-            flags: GameEItemIdFlag::None as u8,
-        })
+        Self::builder(id).build()
+    }
+
+    /// Creates a builder for an item id of a given record.
+    ///
+    /// # Example
+    /// ```rust
+    /// use red4rs::types::{GamedataItemStructure, ItemId, Seed, TweakDbId};
+    ///
+    /// fn crafted_item() -> ItemId {
+    ///     ItemId::builder(TweakDbId::new("Items.Preset_Overture_Default"))
+    ///         .seed(Seed::random())
+    ///         .structure(GamedataItemStructure::Unique)
+    ///         .build()
+    /// }
+    /// ```
+    #[inline]
+    pub const fn builder(id: TweakDbId) -> ItemIdBuilder {
+        ItemIdBuilder {
+            tdbid: id,
+            seed: Seed::new(DEFAULT_ITEM_ID_RNG_SEED),
+            unique_counter: 0,
+            structure: GamedataItemStructure::BlueprintStackable,
+            flags: GameEItemIdFlag::None,
+        }
+    }
+
+    #[inline]
+    pub const fn seed(&self) -> Seed {
+        Seed(self.0.rngSeed)
+    }
+
+    #[inline]
+    pub const fn unique_counter(&self) -> u16 {
+        self.0.uniqueCounter
     }
 
     pub fn structure(&self) -> GamedataItemStructure {
@@ -43,28 +74,138 @@ impl ItemId {
     pub fn is_valid(&self) -> bool {
         unsafe { self.0.IsValid() }
     }
+
+    // the offset of the record in the flat buffer is not part of the identity of the item
+    #[inline]
+    fn key(&self) -> (u32, u8, u32, u16, u8, u8) {
+        let tdbid = self.tdbid();
+        (
+            tdbid.hash(),
+            tdbid.len(),
+            self.0.rngSeed,
+            self.0.uniqueCounter,
+            self.0.structure,
+            self.0.flags,
+        )
+    }
 }
 
 impl fmt::Debug for ItemId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ItemId")
             .field("tdbid", &self.tdbid())
+            .field("seed", &self.seed().value())
+            .field("unique_counter", &self.unique_counter())
             .field("structure", &self.structure())
             .field("flags", &self.flags())
             .finish()
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+impl PartialEq for ItemId {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for ItemId {}
+
+impl PartialOrd for ItemId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ItemId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for ItemId {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// A builder for [`ItemId`], obtained from [`ItemId::builder`].
+#[derive(Debug, Clone, Copy)]
+pub struct ItemIdBuilder {
+    tdbid: TweakDbId,
+    seed: Seed,
+    unique_counter: u16,
+    structure: GamedataItemStructure,
+    flags: GameEItemIdFlag,
+}
+
+impl ItemIdBuilder {
+    /// Sets the seed used to generate the random stats of the item.
+    #[inline]
+    pub const fn seed(mut self, seed: Seed) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the counter that distinguishes items with the same record and seed.
+    #[inline]
+    pub const fn unique_counter(mut self, counter: u16) -> Self {
+        self.unique_counter = counter;
+        self
+    }
+
+    #[inline]
+    pub const fn structure(mut self, structure: GamedataItemStructure) -> Self {
+        self.structure = structure;
+        self
+    }
+
+    #[inline]
+    pub const fn flags(mut self, flags: GameEItemIdFlag) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    #[inline]
+    pub const fn build(self) -> ItemId {
+        ItemId(red::ItemID {
+            tdbid: self.tdbid.to_inner(),
+            rngSeed: self.seed.0,
+            uniqueCounter: self.unique_counter,
+            structure: self.structure as u8,
+            flags: self.flags as u8,
+        })
+    }
+}
+
+/// A seed used by the game to generate the random stats of an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Seed(u32);
 
+impl Seed {
+    #[inline]
+    pub const fn new(value: u32) -> Self {
+        Self(value)
+    }
+
+    /// Generates a new random seed.
+    #[inline]
+    pub fn random() -> Self {
+        Self(crate::random_u64() as u32)
+    }
+
+    #[inline]
+    pub const fn value(self) -> u32 {
+        self.0
+    }
+}
+
 impl Default for Seed {
     fn default() -> Self {
         Self(DEFAULT_ITEM_ID_RNG_SEED)
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
 #[repr(u8)]
 pub enum GamedataItemStructure {
     #[default]
@@ -89,7 +230,7 @@ impl From<u8> for GamedataItemStructure {
 
 /// see [gameEItemIDFlag](https://nativedb.red4ext.com/gameEItemIDFlag)
 /// and [CET initialization](https://github.com/maximegmd/CyberEngineTweaks/blob/v1.27.1/src/scripting/Scripting.cpp#L311).
-#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
 #[repr(u8)]
 pub enum GameEItemIdFlag {
     #[default]
@@ -108,9 +249,11 @@ impl From<u8> for GameEItemIdFlag {
 
 #[cfg(test)]
 mod tests {
+    use std::cmp::Ordering;
+    use std::hash::{DefaultHasher, Hash, Hasher};
     use std::ops::Not;
 
-    use super::{ItemId, TweakDbId};
+    use super::{GameEItemIdFlag, GamedataItemStructure, ItemId, Seed, TweakDbId};
 
     const V0: TweakDbId = TweakDbId::new("Items.FirstAidWhiffV0");
     const V1: TweakDbId = TweakDbId::new("Items.FirstAidWhiffV1");
@@ -121,4 +264,40 @@ mod tests {
         assert!(ItemId::new_from(V0).is_of_tdbid(V0));
         assert!(ItemId::new_from(V0).is_of_tdbid(V1).not());
     }
+
+    #[test]
+    fn builder() {
+        let item = ItemId::builder(V0)
+            .seed(Seed::new(1234))
+            .unique_counter(7)
+            .structure(GamedataItemStructure::Unique)
+            .flags(GameEItemIdFlag::Preview)
+            .build();
+        assert_eq!(item.tdbid(), V0);
+        assert_eq!(item.seed(), Seed::new(1234));
+        assert_eq!(item.unique_counter(), 7);
+        assert_eq!(item.structure(), GamedataItemStructure::Unique);
+        assert_eq!(item.flags(), GameEItemIdFlag::Preview);
+
+        assert_eq!(ItemId::builder(V0).build(), ItemId::new_from(V0));
+        assert_eq!(ItemId::new_from(V0).seed(), Seed::default());
+        assert_ne!(item, ItemId::builder(V0).seed(Seed::new(1234)).build());
+        assert!(ItemId::new_from(V0) < ItemId::builder(V0).unique_counter(1).build());
+    }
+
+    #[test]
+    fn ignores_tdb_offset() {
+        let item = ItemId::new_from(V0);
+        let with_offset = ItemId::new_from(V0.with_tdb_offset(128));
+        assert_eq!(item, with_offset);
+        assert_eq!(item.cmp(&with_offset), Ordering::Equal);
+        assert_eq!(hash(&item), hash(&with_offset));
+        assert_ne!(item, ItemId::new_from(V1.with_tdb_offset(128)));
+    }
+
+    fn hash(item: &ItemId) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        hasher.finish()
+    }
 }
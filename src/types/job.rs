@@ -0,0 +1,35 @@
+use crate::raw::root::RED4ext as red;
+
+/// A handle to a job scheduled on the engine job system.
+#[repr(transparent)]
+pub struct JobHandle(red::JobHandle);
+
+impl JobHandle {
+    #[inline]
+    pub(super) fn from_raw(raw: red::JobHandle) -> Self {
+        Self(raw)
+    }
+
+    /// Makes this job depend on the completion of another job.
+    #[inline]
+    pub fn join(&mut self, other: &JobHandle) {
+        unsafe { self.0.Join(&other.0) }
+    }
+
+    /// Blocks the current thread until the job is finished.
+    #[inline]
+    pub fn wait(mut self) {
+        unsafe {
+            let mut queue = red::JobQueue::new();
+            queue.Wait(&mut self.0);
+            queue.destruct();
+        }
+    }
+}
+
+impl Drop for JobHandle {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { self.0.destruct() }
+    }
+}
@@ -0,0 +1,77 @@
+use std::{fmt, mem, ptr};
+
+use super::RedString;
+use crate::raw::root::RED4ext as red;
+use crate::InvokeError;
+
+/// A localized string. It stores a key that is resolved to the text in the current language
+/// through the localization system.
+#[repr(transparent)]
+pub struct LocalizationString(red::LocalizationString);
+
+impl LocalizationString {
+    /// Creates a new localization string from an unresolved value. The value can either be
+    /// a `LocKey#<id>` reference or a secondary key.
+    #[inline]
+    pub fn new(value: impl Into<RedString>) -> Self {
+        let value = unsafe { mem::transmute::<RedString, red::CString>(value.into()) };
+        Self(red::LocalizationString {
+            value,
+            ..Default::default()
+        })
+    }
+
+    /// Creates a new localization string referencing a numeric localization key.
+    #[inline]
+    pub fn from_key(key: u64) -> Self {
+        Self::new(format!("LocKey#{key}"))
+    }
+
+    /// Returns the unresolved value of the string.
+    #[inline]
+    pub fn key(&self) -> &RedString {
+        unsafe { &*(&self.0.value as *const red::CString as *const RedString) }
+    }
+
+    /// Resolves the string to the text in the current language. If there's no localized text
+    /// for the key, the key itself is returned.
+    pub fn resolve(&self) -> Result<String, InvokeError> {
+        crate::call!("GetLocalizedText" (self.key().clone()) -> String)
+    }
+}
+
+impl Default for LocalizationString {
+    #[inline]
+    fn default() -> Self {
+        Self::new(RedString::new())
+    }
+}
+
+impl Clone for LocalizationString {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::new(self.key().clone())
+    }
+}
+
+impl From<RedString> for LocalizationString {
+    #[inline]
+    fn from(value: RedString) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for LocalizationString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LocalizationString")
+            .field(self.key())
+            .finish()
+    }
+}
+
+impl Drop for LocalizationString {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(&mut self.0.value as *mut red::CString as *mut RedString) };
+    }
+}
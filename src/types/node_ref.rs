@@ -0,0 +1,181 @@
+use std::hash::Hash;
+use std::{fmt, slice};
+
+use crate::raw::root::RED4ext as red;
+use crate::{fnv1a64, fnv1a64_seeded};
+
+/// A reference to a world node, identified by the hash of its `$/path/to/node` path.
+///
+/// Paths are normalized before hashing: the `$/` prefix is added when it's missing, repeated
+/// separators are collapsed and trailing separators are removed, so `$/a/b`, `/a//b/` and
+/// `a/b` all refer to the same node.
+///
+/// # Example
+/// ```rust
+/// use red4rs::types::NodeRef;
+///
+/// const DISTRICT: NodeRef = NodeRef::new("$/03_night_city/district");
+///
+/// fn building() -> NodeRef {
+///     DISTRICT.join("building")
+/// }
+/// ```
+#[derive(Default, Clone, Copy)]
+#[repr(transparent)]
+pub struct NodeRef(red::NodeRef);
+
+impl NodeRef {
+    /// Creates a new node reference from a path. An empty path results in an undefined
+    /// reference.
+    #[inline]
+    pub const fn new(path: &str) -> Self {
+        if path.is_empty() {
+            return Self::undefined();
+        }
+        let hash = hash_components(fnv1a64("$"), path.as_bytes());
+        if hash == fnv1a64("$") {
+            return Self::root();
+        }
+        Self::from_hash(hash)
+    }
+
+    /// Returns the reference to the root node, `$/`.
+    #[inline]
+    pub const fn root() -> Self {
+        Self::from_hash(fnv1a64("$/"))
+    }
+
+    /// Returns an undefined reference.
+    #[inline]
+    pub const fn undefined() -> Self {
+        Self::from_hash(0)
+    }
+
+    /// Returns a reference to a node nested under this one. An undefined reference is treated
+    /// as the root.
+    #[inline]
+    pub const fn join(self, path: &str) -> Self {
+        let base = if self.is_defined() && self.0.hash != Self::root().0.hash {
+            self.0.hash
+        } else {
+            fnv1a64("$")
+        };
+        let hash = hash_components(base, path.as_bytes());
+        if hash == fnv1a64("$") {
+            return Self::root();
+        }
+        Self::from_hash(hash)
+    }
+
+    #[inline]
+    pub const fn is_defined(self) -> bool {
+        self.0.hash != 0
+    }
+
+    #[inline]
+    pub const fn hash(self) -> u64 {
+        self.0.hash
+    }
+
+    #[inline]
+    const fn from_hash(hash: u64) -> Self {
+        Self(red::NodeRef { hash })
+    }
+}
+
+// hashes each non-empty component of the path preceded by a separator, skipping the `$` root
+// marker if it's present
+const fn hash_components(seed: u64, path: &[u8]) -> u64 {
+    let mut hash = seed;
+    let mut i = if !path.is_empty() && path[0] == b'$' {
+        1
+    } else {
+        0
+    };
+    let mut separated = true;
+    while i < path.len() {
+        let byte = path[i];
+        if byte == b'/' {
+            separated = true;
+        } else {
+            if separated {
+                hash = fnv1a64_seeded(b"/", hash);
+                separated = false;
+            }
+            hash = fnv1a64_seeded(slice::from_ref(&path[i]), hash);
+        }
+        i += 1;
+    }
+    hash
+}
+
+impl PartialEq for NodeRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.hash == other.0.hash
+    }
+}
+
+impl Eq for NodeRef {}
+
+impl Hash for NodeRef {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash.hash(state);
+    }
+}
+
+impl From<u64> for NodeRef {
+    fn from(hash: u64) -> Self {
+        Self::from_hash(hash)
+    }
+}
+
+impl From<NodeRef> for u64 {
+    fn from(NodeRef(red::NodeRef { hash }): NodeRef) -> Self {
+        hash
+    }
+}
+
+impl fmt::Debug for NodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NodeRef({:#018X})", self.0.hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NodeRef;
+
+    #[test]
+    fn known_hashes() {
+        assert_eq!(
+            NodeRef::new("$/03_night_city").hash(),
+            0x4456_66BF_489E_C042
+        );
+        assert_eq!(
+            NodeRef::new("$/03_night_city/district").hash(),
+            0x8534_A60C_B91B_6395
+        );
+        assert_eq!(NodeRef::root().hash(), 0x07B8_1907_B483_5714);
+        assert_eq!(NodeRef::new("").hash(), 0);
+    }
+
+    #[test]
+    fn normalization() {
+        let node = NodeRef::new("$/03_night_city/district");
+        assert_eq!(NodeRef::new("03_night_city/district"), node);
+        assert_eq!(NodeRef::new("/03_night_city//district/"), node);
+        assert_eq!(NodeRef::new("$//03_night_city/district//"), node);
+        assert_eq!(NodeRef::new("$"), NodeRef::root());
+        assert_eq!(NodeRef::new("/"), NodeRef::root());
+    }
+
+    #[test]
+    fn composition() {
+        let node = NodeRef::new("$/03_night_city/district");
+        assert_eq!(NodeRef::new("$/03_night_city").join("district"), node);
+        assert_eq!(NodeRef::new("$/03_night_city/").join("/district/"), node);
+        assert_eq!(NodeRef::root().join("03_night_city/district"), node);
+        assert_eq!(NodeRef::undefined().join("03_night_city/district"), node);
+        assert_eq!(node.join(""), node);
+    }
+}
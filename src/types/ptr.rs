@@ -0,0 +1,311 @@
+use std::collections::BTreeSet;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, PoisonError};
+use std::{fmt, mem, ptr};
+
+use super::refs::RefCount;
+use super::IAllocator;
+use crate::raw::root::RED4ext as red;
+
+// addresses of the reference counts and values allocated on the Rust side, these are the only
+// ones that can be freed by Rust, the game frees its own with allocators and destructors that
+// are specific to the type
+static RUST_OWNED: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+/// A reference-counted pointer used by native engine structures. Unlike [`Ref`](super::Ref),
+/// it can point to any type, not only to classes.
+///
+/// Values are only destroyed on the Rust side if they were created with [`SharedPtr::new`].
+/// When the last reference to a value created by the game is dropped on the Rust side, the
+/// value is leaked, because the game's deleter for it is not known.
+#[repr(transparent)]
+pub struct SharedPtr<T>(red::SharedPtrBase<T>);
+
+impl<T> SharedPtr<T> {
+    /// Creates a new pointer to a value allocated from the engine pool.
+    pub fn new(value: T) -> Self {
+        unsafe {
+            let instance = alloc::<T>();
+            instance.write(value);
+            let ref_count = alloc::<RefCount>();
+            ref_count.write_bytes(0, 1);
+            (*ref_count).strong().store(1, Ordering::Relaxed);
+            (*ref_count).weak_refs().store(1, Ordering::Relaxed);
+            set_rust_owned(ref_count as usize);
+
+            Self(red::SharedPtrBase {
+                instance,
+                refCount: ref_count.cast(),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Creates a null pointer.
+    #[inline]
+    pub fn null() -> Self {
+        Self(red::SharedPtrBase::default())
+    }
+
+    /// Returns `true` if the pointer is null.
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.0.instance.is_null()
+    }
+
+    /// Returns a reference to the value, if the pointer is not null.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        unsafe { self.0.instance.as_ref() }
+    }
+
+    /// Returns the number of strong references to the value.
+    #[inline]
+    pub fn strong_count(&self) -> u32 {
+        self.ref_count()
+            .map_or(0, |cnt| cnt.strong().load(Ordering::Relaxed))
+    }
+
+    #[inline]
+    fn ref_count(&self) -> Option<&RefCount> {
+        unsafe { self.0.refCount.cast::<RefCount>().as_ref() }
+    }
+}
+
+impl<T> Default for SharedPtr<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T> Clone for SharedPtr<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        if let Some(cnt) = self.ref_count() {
+            cnt.strong().fetch_add(1, Ordering::Relaxed);
+        }
+        Self(red::SharedPtrBase {
+            instance: self.0.instance,
+            refCount: self.0.refCount,
+            ..Default::default()
+        })
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SharedPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedPtr").field(&self.get()).finish()
+    }
+}
+
+impl<T> Drop for SharedPtr<T> {
+    fn drop(&mut self) {
+        let Some(cnt) = self.ref_count() else {
+            return;
+        };
+        if cnt.strong().fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+        if !take_rust_owned(self.0.refCount as usize) {
+            return;
+        }
+        unsafe {
+            if !self.0.instance.is_null() {
+                ptr::drop_in_place(self.0.instance);
+                IAllocator::container().free(self.0.instance);
+            }
+            // the strong references collectively hold one weak reference
+            if cnt.weak_refs().fetch_sub(1, Ordering::AcqRel) == 1 {
+                IAllocator::container().free(self.0.refCount);
+            }
+        }
+    }
+}
+
+unsafe impl<T: Send + Sync> Send for SharedPtr<T> {}
+unsafe impl<T: Send + Sync> Sync for SharedPtr<T> {}
+
+/// An owning pointer used by native engine structures.
+///
+/// Values are only destroyed on the Rust side if they were created with [`UniquePtr::new`],
+/// values created by the game are leaked when the pointer is dropped, because the game's
+/// deleter for them is not known.
+#[repr(transparent)]
+pub struct UniquePtr<T>(*mut T);
+
+impl<T> UniquePtr<T> {
+    /// Creates a new pointer to a value allocated from the engine pool.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        unsafe {
+            let instance = alloc::<T>();
+            instance.write(value);
+            set_rust_owned(instance as usize);
+            Self(instance)
+        }
+    }
+
+    /// Creates a null pointer.
+    #[inline]
+    pub const fn null() -> Self {
+        Self(ptr::null_mut())
+    }
+
+    /// Returns `true` if the pointer is null.
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.0.is_null()
+    }
+
+    /// Returns a reference to the value, if the pointer is not null.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        unsafe { self.0.as_ref() }
+    }
+
+    /// Returns a mutable reference to the value, if the pointer is not null.
+    #[inline]
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.0.as_mut() }
+    }
+
+    /// Moves the value out of the pointer and releases its memory. The memory of values created
+    /// by the game is leaked.
+    pub fn into_inner(self) -> Option<T> {
+        let this = mem::ManuallyDrop::new(self);
+        if this.0.is_null() {
+            return None;
+        }
+        unsafe {
+            let value = this.0.read();
+            if take_rust_owned(this.0 as usize) {
+                IAllocator::container().free(this.0);
+            }
+            Some(value)
+        }
+    }
+}
+
+impl<T> Default for UniquePtr<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for UniquePtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("UniquePtr").field(&self.get()).finish()
+    }
+}
+
+impl<T> Drop for UniquePtr<T> {
+    #[inline]
+    fn drop(&mut self) {
+        if !self.0.is_null() && take_rust_owned(self.0 as usize) {
+            unsafe {
+                ptr::drop_in_place(self.0);
+                IAllocator::container().free(self.0);
+            }
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for UniquePtr<T> {}
+unsafe impl<T: Sync> Sync for UniquePtr<T> {}
+
+#[inline]
+fn set_rust_owned(addr: usize) {
+    RUST_OWNED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(addr);
+}
+
+#[inline]
+fn take_rust_owned(addr: usize) -> bool {
+    RUST_OWNED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&addr)
+}
+
+unsafe fn alloc<T>() -> *mut T {
+    let size = mem::size_of::<T>().max(1);
+    let align = mem::align_of::<T>().max(8);
+    let ptr = unsafe { IAllocator::container().alloc_aligned::<T>(size as u32, align as u32) };
+    assert!(!ptr.is_null(), "engine allocation should not fail");
+    ptr
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::{SharedPtr, UniquePtr};
+    use crate::types::allocator::mock;
+
+    #[derive(Debug)]
+    struct Tracked(Arc<AtomicUsize>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn shared_ptr() {
+        mock::install();
+        let drops = Arc::new(AtomicUsize::new(0));
+        let ptr = SharedPtr::new(Tracked(drops.clone()));
+        let other = ptr.clone();
+        assert_eq!(ptr.strong_count(), 2);
+        assert!(Arc::ptr_eq(&ptr.get().unwrap().0, &drops));
+
+        drop(ptr);
+        assert_eq!(other.strong_count(), 1);
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        drop(other);
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn null_shared_ptr() {
+        let ptr = SharedPtr::<i32>::null();
+        assert!(ptr.is_null());
+        assert_eq!(ptr.get(), None);
+        assert_eq!(ptr.clone().strong_count(), 0);
+    }
+
+    #[test]
+    fn unique_ptr() {
+        mock::install();
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut ptr = UniquePtr::new(Tracked(drops.clone()));
+        assert!(ptr.get_mut().is_some());
+        drop(ptr);
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+
+        let ptr = UniquePtr::new(Tracked(drops.clone()));
+        let value = ptr.into_inner().unwrap();
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+        drop(value);
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+        assert!(UniquePtr::<i32>::null().into_inner().is_none());
+    }
+
+    #[test]
+    fn game_owned_unique_ptr() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let instance = Box::into_raw(Box::new(Tracked(drops.clone())));
+        // a pointer that was not allocated on the Rust side is left alone
+        drop(UniquePtr(instance));
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+
+        drop(unsafe { Box::from_raw(instance) });
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+    }
+}
@@ -334,16 +334,16 @@ impl<T> Clone for BaseRef<T> {
 
 #[derive(Debug, Clone, Copy)]
 #[repr(transparent)]
-struct RefCount(red::RefCnt);
+pub(super) struct RefCount(red::RefCnt);
 
 impl RefCount {
     #[inline]
-    fn strong(&self) -> &AtomicU32 {
+    pub(super) fn strong(&self) -> &AtomicU32 {
         unsafe { AtomicU32::from_ptr(&self.0.strongRefs as *const _ as _) }
     }
 
     #[inline]
-    fn weak_refs(&self) -> &AtomicU32 {
+    pub(super) fn weak_refs(&self) -> &AtomicU32 {
         unsafe { AtomicU32::from_ptr(&self.0.weakRefs as *const _ as _) }
     }
 }
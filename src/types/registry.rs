@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+use super::res::{self, ResourcePath, ResourcePathError};
+use super::{CName, TweakDbId};
+use crate::fnv1a64;
+
+/// A registry of known strings used to resolve hashes back to the strings they were created
+/// from. It's consulted by the `Debug` and `Display` implementations of [`CName`],
+/// [`TweakDbId`], [`ResourcePath`], [`ResRef`](super::ResRef) and [`RaRef`](super::RaRef),
+/// which fall back to the raw hash when a string is not known.
+///
+/// The registry does not depend on the game, so it can also be used by offline tools.
+/// Registered strings are never freed.
+///
+/// # Example
+/// ```rust
+/// use std::fs::File;
+/// use std::io::{self, BufReader};
+///
+/// use red4rs::types::HashRegistry;
+///
+/// fn load_hashes() -> io::Result<()> {
+///     let file = BufReader::new(File::open("archivehashes.csv")?);
+///     HashRegistry::global().load_resource_paths(file)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct HashRegistry {
+    names: Table,
+    tweak_db_ids: Table,
+    resource_paths: Table,
+}
+
+impl HashRegistry {
+    /// Returns the registry shared by the whole process.
+    #[inline]
+    pub fn global() -> &'static Self {
+        static INSTANCE: OnceLock<HashRegistry> = OnceLock::new();
+        INSTANCE.get_or_init(Self::default)
+    }
+
+    /// Registers a name and returns its [`CName`].
+    pub fn register_name(&self, name: &str) -> CName {
+        let cname = CName::new(name);
+        self.names.insert(u64::from(cname), name);
+        cname
+    }
+
+    /// Registers a TweakDB record or flat name and returns its [`TweakDbId`].
+    ///
+    /// # Panics
+    /// Panics if the name is longer than 255 bytes.
+    pub fn register_tweak_db_id(&self, name: &str) -> TweakDbId {
+        let id = TweakDbId::new(name);
+        self.tweak_db_ids.insert(tweak_db_id_key(id), name);
+        id
+    }
+
+    /// Registers a resource path and returns it. The path is normalized the same way as in
+    /// [`ResourcePath::new`].
+    pub fn register_resource_path(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<ResourcePath, ResourcePathError> {
+        let sanitized = res::sanitize_path(path)?;
+        let hash = fnv1a64(&sanitized);
+        self.resource_paths.insert(hash, &sanitized);
+        Ok(ResourcePath::from_hash(hash))
+    }
+
+    /// Returns the string a [`CName`] was created from, if it's known.
+    #[inline]
+    pub fn resolve_name(&self, name: CName) -> Option<&'static str> {
+        self.names.get(u64::from(name))
+    }
+
+    /// Returns the string a [`TweakDbId`] was created from, if it's known.
+    #[inline]
+    pub fn resolve_tweak_db_id(&self, id: TweakDbId) -> Option<&'static str> {
+        self.tweak_db_ids.get(tweak_db_id_key(id))
+    }
+
+    /// Returns the resource path with the given hash, if it's known.
+    #[inline]
+    pub fn resolve_resource_path(&self, hash: u64) -> Option<&'static str> {
+        self.resource_paths.get(hash)
+    }
+
+    /// Registers names read from a list with one name per line. Returns the number of names
+    /// read.
+    pub fn load_names(&self, reader: impl BufRead) -> io::Result<usize> {
+        load_lines(reader, |line| {
+            self.register_name(line);
+        })
+    }
+
+    /// Registers TweakDB names read from a list with one name per line. Names longer than 255
+    /// bytes are skipped. Returns the number of names read.
+    pub fn load_tweak_db_ids(&self, reader: impl BufRead) -> io::Result<usize> {
+        load_lines(reader, |line| {
+            if line.len() <= u8::MAX as usize {
+                self.register_tweak_db_id(line);
+            }
+        })
+    }
+
+    /// Registers resource paths read from a list with one path per line. Invalid paths are
+    /// skipped. Returns the number of paths read.
+    pub fn load_resource_paths(&self, reader: impl BufRead) -> io::Result<usize> {
+        load_lines(reader, |line| {
+            self.register_resource_path(line).ok();
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Table {
+    entries: RwLock<HashMap<u64, &'static str>>,
+}
+
+impl Table {
+    fn get(&self, hash: u64) -> Option<&'static str> {
+        self.entries
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(&hash)
+            .copied()
+    }
+
+    fn insert(&self, hash: u64, str: &str) {
+        let mut entries = self.entries.write().unwrap_or_else(|err| err.into_inner());
+        entries.entry(hash).or_insert_with(|| Box::leak(str.into()));
+    }
+}
+
+// the offset is not part of the identity of a TweakDbId, only the hash and the length are
+#[inline]
+fn tweak_db_id_key(id: TweakDbId) -> u64 {
+    u64::from(id.len()) << 32 | u64::from(id.hash())
+}
+
+// reads non-empty lines, in CSV lists with a hash column only the first column is used
+fn load_lines(reader: impl BufRead, mut f: impl FnMut(&str)) -> io::Result<usize> {
+    let mut count = 0;
+    for line in reader.lines() {
+        let line = line?;
+        let str = line.split(',').next().unwrap_or_default().trim();
+        if !str.is_empty() {
+            f(str);
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HashRegistry;
+    use crate::types::{CName, TweakDbId};
+
+    #[test]
+    fn register_and_resolve() {
+        let registry = HashRegistry::default();
+        let cname = registry.register_name("PlayerPuppet");
+        assert_eq!(cname, CName::new("PlayerPuppet"));
+        assert_eq!(registry.resolve_name(cname), Some("PlayerPuppet"));
+        assert_eq!(registry.resolve_name(CName::new("Other")), None);
+
+        let id = registry.register_tweak_db_id("Items.FirstAidWhiffV0");
+        assert_eq!(
+            registry.resolve_tweak_db_id(id.with_tdb_offset(64)),
+            Some("Items.FirstAidWhiffV0")
+        );
+
+        let path = registry
+            .register_resource_path("BASE/Characters/Player.ent")
+            .unwrap();
+        assert_eq!(
+            registry.resolve_resource_path(path.hash()),
+            Some("base\\characters\\player.ent")
+        );
+        assert!(registry.register_resource_path("..\\player.ent").is_err());
+    }
+
+    #[test]
+    fn load_lists() {
+        let registry = HashRegistry::default();
+        let names = "PlayerPuppet\n\n  gameObject  \n";
+        assert_eq!(registry.load_names(names.as_bytes()).unwrap(), 2);
+        assert_eq!(
+            registry.resolve_name(CName::new("gameObject")),
+            Some("gameObject")
+        );
+
+        let ids = "Items.FirstAidWhiffV0,90628141458\n";
+        assert_eq!(registry.load_tweak_db_ids(ids.as_bytes()).unwrap(), 1);
+        assert_eq!(
+            registry.resolve_tweak_db_id(TweakDbId::from(90_628_141_458)),
+            Some("Items.FirstAidWhiffV0")
+        );
+
+        let paths = "base\\a.ent\n..\\invalid.ent\n";
+        assert_eq!(registry.load_resource_paths(paths.as_bytes()).unwrap(), 2);
+        assert!(registry
+            .resolve_resource_path(crate::fnv1a64("base\\a.ent"))
+            .is_some());
+    }
+}
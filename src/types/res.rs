@@ -1,28 +1,171 @@
 use std::hash::Hash;
 use std::marker::PhantomData;
 use std::path::Path;
+use std::time::{Duration, Instant};
+use std::{fmt, ptr, thread};
 
 use thiserror::Error;
 
-use crate::fnv1a64;
+use super::{HashRegistry, SharedPtr};
 use crate::raw::root::RED4ext as red;
+use crate::{fnv1a64, VoidPtr};
 
 pub const MAX_LENGTH: usize = 216;
 
-#[derive(Debug, Default, Clone, Copy)]
+/// A hash of a path to a resource in the game archives, e.g. `base\characters\player.ent`.
+///
+/// Paths created with [`ResourcePath::new`] are normalized and retained in the global
+/// [`HashRegistry`], so they can be retrieved later with [`ResourcePath::as_str`]. Paths hashed
+/// at compile time with [`ResourcePath::from_static`] are used as-is, so they have to be
+/// canonical already.
+///
+/// # Example
+/// ```rust
+/// use red4rs::types::ResourcePath;
+///
+/// const PLAYER: ResourcePath = ResourcePath::from_static("base\\characters\\player.ent");
+///
+/// fn appearances() -> ResourcePath {
+///     let dir = ResourcePath::new("base/characters/appearances").unwrap();
+///     dir.join("player.app").unwrap()
+/// }
+/// ```
+#[derive(Default, Clone, Copy)]
+#[repr(transparent)]
+pub struct ResourcePath(red::ResourcePath);
+
+impl ResourcePath {
+    /// Creates a new resource path, normalizing it and retaining the normalized string.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, ResourcePathError> {
+        HashRegistry::global().register_resource_path(path)
+    }
+
+    /// Creates a resource path from a path that's already in the canonical form, i.e.
+    /// lowercase with backslash separators. The string is not retained.
+    #[inline]
+    pub const fn from_static(path: &'static str) -> Self {
+        Self::from_hash(fnv1a64(path))
+    }
+
+    /// Returns the path string, if it's known to the [`HashRegistry`].
+    #[inline]
+    pub fn as_str(self) -> Option<&'static str> {
+        HashRegistry::global().resolve_resource_path(self.0.hash)
+    }
+
+    /// Returns the extension of the file the path points to, if the path string is known and
+    /// has one.
+    pub fn extension(self) -> Option<&'static str> {
+        let name = self.as_str()?.rsplit('\\').next()?;
+        match name.rsplit_once('.')? {
+            ("", _) => None,
+            (_, ext) => Some(ext),
+        }
+    }
+
+    /// Returns a path nested under this one. Fails with [`ResourcePathError::Unknown`] if the
+    /// path string is not known.
+    pub fn join(self, path: impl AsRef<Path>) -> Result<Self, ResourcePathError> {
+        let base = self.as_str().ok_or(ResourcePathError::Unknown)?;
+        Self::new(Path::new(base).join(path))
+    }
+
+    #[inline]
+    pub const fn is_defined(self) -> bool {
+        self.0.hash != 0
+    }
+
+    #[inline]
+    pub const fn hash(self) -> u64 {
+        self.0.hash
+    }
+
+    #[inline]
+    pub(super) const fn from_hash(hash: u64) -> Self {
+        Self(red::ResourcePath { hash })
+    }
+}
+
+impl PartialEq for ResourcePath {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.hash == other.0.hash
+    }
+}
+
+impl Eq for ResourcePath {}
+
+impl PartialOrd for ResourcePath {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ResourcePath {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.hash.cmp(&other.0.hash)
+    }
+}
+
+impl Hash for ResourcePath {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash.hash(state);
+    }
+}
+
+impl From<u64> for ResourcePath {
+    fn from(hash: u64) -> Self {
+        Self::from_hash(hash)
+    }
+}
+
+impl From<ResourcePath> for u64 {
+    fn from(path: ResourcePath) -> Self {
+        path.0.hash
+    }
+}
+
+impl fmt::Display for ResourcePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_str() {
+            Some(path) => f.write_str(path),
+            None => write!(f, "{:#018X}", self.0.hash),
+        }
+    }
+}
+
+impl fmt::Debug for ResourcePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ResourcePath")
+            .field(&DebugPath(self.0.hash))
+            .finish()
+    }
+}
+
+#[derive(Default, Clone, Copy)]
 #[repr(transparent)]
 pub struct RaRef<T>(red::RaRef, PhantomData<T>);
 
 impl<T> RaRef<T> {
     pub fn new(path: impl AsRef<Path>) -> Result<Self, ResourcePathError> {
-        Ok(Self(
-            red::RaRef {
-                path: red::ResourcePath {
-                    hash: encode_path(path)?,
-                },
-            },
-            PhantomData,
-        ))
+        ResourcePath::new(path).map(Self::from)
+    }
+
+    #[inline]
+    pub fn path(&self) -> ResourcePath {
+        ResourcePath(self.0.path)
+    }
+
+    /// Starts loading the resource asynchronously and returns a token that gives access to it
+    /// once it's loaded.
+    #[inline]
+    pub fn load(&self) -> ResourceToken<T> {
+        ResourceToken::load(self.path())
+    }
+}
+
+impl<T> From<ResourcePath> for RaRef<T> {
+    fn from(ResourcePath(path): ResourcePath) -> Self {
+        Self(red::RaRef { path }, PhantomData)
     }
 }
 
@@ -34,6 +177,14 @@ impl<T> PartialEq for RaRef<T> {
 
 impl<T> Eq for RaRef<T> {}
 
+impl<T> fmt::Debug for RaRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RaRef")
+            .field(&DebugPath(self.0.path.hash))
+            .finish()
+    }
+}
+
 impl<T> PartialOrd for RaRef<T> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
@@ -52,19 +203,26 @@ impl<T> Hash for RaRef<T> {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Default)]
 #[repr(transparent)]
 pub struct ResRef(red::ResRef);
 
 impl ResRef {
     pub fn new(path: impl AsRef<Path>) -> Result<Self, ResourcePathError> {
-        Ok(Self(red::ResRef {
-            resource: red::RaRef {
-                path: red::ResourcePath {
-                    hash: encode_path(path)?,
-                },
-            },
-        }))
+        ResourcePath::new(path).map(Self::from)
+    }
+
+    #[inline]
+    pub fn path(&self) -> ResourcePath {
+        ResourcePath(self.0.resource.path)
+    }
+}
+
+impl From<ResourcePath> for ResRef {
+    fn from(ResourcePath(path): ResourcePath) -> Self {
+        Self(red::ResRef {
+            resource: red::RaRef { path },
+        })
     }
 }
 
@@ -76,6 +234,14 @@ impl PartialEq for ResRef {
 
 impl Eq for ResRef {}
 
+impl fmt::Debug for ResRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ResRef")
+            .field(&DebugPath(self.0.resource.path.hash))
+            .finish()
+    }
+}
+
 impl PartialOrd for ResRef {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
@@ -102,7 +268,7 @@ impl Clone for ResRef {
     }
 }
 
-fn encode_path(path: impl AsRef<Path>) -> Result<u64, ResourcePathError> {
+pub(super) fn sanitize_path(path: impl AsRef<Path>) -> Result<String, ResourcePathError> {
     let sanitized = path
         .as_ref()
         .to_str()
@@ -130,7 +296,343 @@ fn encode_path(path: impl AsRef<Path>) -> Result<u64, ResourcePathError> {
     {
         return Err(ResourcePathError::NotCanonical);
     }
-    Ok(fnv1a64(&sanitized))
+    Ok(sanitized)
+}
+
+/// A reference to a resource that's loaded together with the object that contains it.
+#[repr(transparent)]
+pub struct ResourceRef<T>(red::ResourceReference<T>);
+
+impl<T> ResourceRef<T> {
+    #[inline]
+    pub fn path(&self) -> ResourcePath {
+        ResourcePath(self.0.path)
+    }
+
+    /// Returns the token of the resource.
+    #[inline]
+    pub fn token(&self) -> &ResourceToken<T> {
+        unsafe { &*(&self.0.token as *const _ as *const ResourceToken<T>) }
+    }
+
+    /// Returns `true` if the resource has been loaded successfully.
+    #[inline]
+    pub fn is_loaded(&self) -> bool {
+        self.token().is_loaded()
+    }
+
+    /// Returns a reference to the resource, if it has been loaded.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        self.token().get()
+    }
+}
+
+impl<T> fmt::Debug for ResourceRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ResourceRef")
+            .field(&DebugPath(self.0.path.hash))
+            .finish()
+    }
+}
+
+/// A token of a resource being loaded asynchronously by the engine, obtained from
+/// [`RaRef::load`]. The loading state can be polled, waited for off the main thread, or
+/// observed with a callback.
+///
+/// # Example
+/// ```rust
+/// use red4rs::types::RaRef;
+///
+/// fn load<T: 'static>(template: RaRef<T>) {
+///     template.load().on_loaded(|result| match result {
+///         Ok(resource) => {
+///             // use the resource
+///         }
+///         Err(err) => {
+///             // handle the error
+///         }
+///     });
+/// }
+/// ```
+#[repr(transparent)]
+pub struct ResourceToken<T>(SharedPtr<red::ResourceToken<T>>);
+
+impl<T> ResourceToken<T> {
+    fn load(path: ResourcePath) -> Self {
+        unsafe {
+            let load_async = crate::fn_from_hash!(
+                ResourceLoader_LoadAsync,
+                unsafe extern "C" fn(
+                    *mut red::ResourceLoader,
+                    VoidPtr,
+                    red::ResourcePath,
+                ) -> VoidPtr
+            );
+            let mut token = Self(SharedPtr::null());
+            load_async(
+                red::ResourceLoader::Get(),
+                &mut token as *mut _ as VoidPtr,
+                path.0,
+            );
+            token
+        }
+    }
+
+    /// Returns the path of the resource.
+    #[inline]
+    pub fn path(&self) -> ResourcePath {
+        self.0
+            .get()
+            .map_or_else(ResourcePath::default, |token| ResourcePath(token.path))
+    }
+
+    /// Returns `true` if loading has finished, whether it succeeded or not.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.0
+            .get()
+            .is_none_or(|token| unsafe { ptr::read_volatile(&token.finished) })
+    }
+
+    /// Returns `true` if the resource has been loaded successfully.
+    #[inline]
+    pub fn is_loaded(&self) -> bool {
+        self.get().is_some()
+    }
+
+    /// Returns `true` if loading has finished without producing the resource.
+    #[inline]
+    pub fn is_failed(&self) -> bool {
+        self.is_finished() && !self.is_loaded()
+    }
+
+    /// Returns a reference to the resource, if it has been loaded.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        self.resource_ptr()?.get()
+    }
+
+    /// Returns a shared pointer to the resource, if it has been loaded.
+    #[inline]
+    pub fn resource(&self) -> Option<SharedPtr<T>> {
+        self.resource_ptr().filter(|ptr| !ptr.is_null()).cloned()
+    }
+
+    /// Blocks the current thread until loading is finished and returns the resource. Fails
+    /// with [`ResourceLoadError::TimedOut`] if loading does not finish within `timeout`.
+    /// Waiting on the main thread would stall the game, so it fails with
+    /// [`ResourceLoadError::MainThread`] there.
+    pub fn wait(self, timeout: Duration) -> Result<SharedPtr<T>, ResourceLoadError> {
+        if crate::is_main_thread() {
+            return Err(ResourceLoadError::MainThread);
+        }
+        let deadline = Instant::now() + timeout;
+        while !self.is_finished() {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(ResourceLoadError::TimedOut);
+            }
+            thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+        self.resource().ok_or(ResourceLoadError::Failed)
+    }
+
+    /// Calls the callback with the resource once loading is finished. The callback is always
+    /// called from a background thread shared by all the pending tokens, even if loading has
+    /// finished already. Callbacks that have not run by the time the plugin is unloaded are
+    /// dropped without being called.
+    pub fn on_loaded<F>(self, callback: F)
+    where
+        F: FnOnce(Result<SharedPtr<T>, ResourceLoadError>) + Send + 'static,
+        T: 'static,
+    {
+        let token = SendToken(self);
+        let mut callback = Some(callback);
+        pending::QUEUE.schedule(Box::new(move || {
+            let token = token.get();
+            if !token.is_finished() {
+                return false;
+            }
+            if let Some(callback) = callback.take() {
+                callback(token.resource().ok_or(ResourceLoadError::Failed));
+            }
+            true
+        }));
+    }
+
+    #[inline]
+    fn resource_ptr(&self) -> Option<&SharedPtr<T>> {
+        let token = self.0.get()?;
+        if !unsafe { ptr::read_volatile(&token.finished) } {
+            return None;
+        }
+        Some(unsafe { &*(&token.resource as *const _ as *const SharedPtr<T>) })
+    }
+}
+
+impl<T> Clone for ResourceToken<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> fmt::Debug for ResourceToken<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceToken")
+            .field("path", &DebugPath(self.path().hash()))
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+// the token and the resource it hands out are reference counted atomically and created by the
+// loader on its worker threads, so they can be moved to another thread even if the bindings of
+// the resource type contain raw pointers
+struct SendToken<T>(ResourceToken<T>);
+
+impl<T> SendToken<T> {
+    #[inline]
+    fn get(&self) -> &ResourceToken<T> {
+        &self.0
+    }
+}
+
+unsafe impl<T> Send for SendToken<T> {}
+
+// the interval at which unfinished tokens are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Stops the thread that runs the pending [`ResourceToken::on_loaded`] callbacks and waits for
+/// it to exit. The callbacks are code of the plugin, so they can't outlive it.
+pub(crate) fn stop_pending_loads() {
+    pending::QUEUE.stop();
+}
+
+mod pending {
+    use std::mem;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+    use std::thread::{self, JoinHandle};
+
+    use super::POLL_INTERVAL;
+
+    // a task returns `true` once it's done
+    pub(super) type Task = Box<dyn FnMut() -> bool + Send>;
+
+    pub(super) static QUEUE: Queue = Queue::new();
+
+    pub(super) struct Queue {
+        state: Mutex<State>,
+        wake: Condvar,
+    }
+
+    struct State {
+        tasks: Vec<Task>,
+        worker: Option<JoinHandle<()>>,
+        stopped: bool,
+    }
+
+    impl Queue {
+        pub(super) const fn new() -> Self {
+            Self {
+                state: Mutex::new(State {
+                    tasks: Vec::new(),
+                    worker: None,
+                    stopped: false,
+                }),
+                wake: Condvar::new(),
+            }
+        }
+
+        // the tasks are polled by a single thread, started with the first task and kept until
+        // the queue is stopped, tasks scheduled after that are dropped
+        pub(super) fn schedule(&'static self, task: Task) {
+            let mut state = self.lock();
+            if state.stopped {
+                return;
+            }
+            state.tasks.push(task);
+            if state.worker.is_none() {
+                state.worker = Some(thread::spawn(|| self.run()));
+            }
+            self.wake.notify_one();
+        }
+
+        // the pending tasks are dropped without being run
+        pub(super) fn stop(&self) {
+            let worker = {
+                let mut state = self.lock();
+                state.stopped = true;
+                state.worker.take()
+            };
+            self.wake.notify_all();
+            if let Some(worker) = worker {
+                worker.join().ok();
+            }
+            // the worker puts back the tasks it was polling before it exits
+            let tasks = mem::take(&mut self.lock().tasks);
+            drop(tasks);
+        }
+
+        fn run(&self) {
+            let mut state = self.lock();
+            loop {
+                state = self
+                    .wake
+                    .wait_while(state, |state| state.tasks.is_empty() && !state.stopped)
+                    .unwrap_or_else(PoisonError::into_inner);
+                if state.stopped {
+                    return;
+                }
+                // the tasks are run without the lock, so that they can schedule new ones
+                let mut current = mem::take(&mut state.tasks);
+                drop(state);
+                // a panicking task is dropped, it must not stop the other ones
+                current.retain_mut(|task| {
+                    !panic::catch_unwind(AssertUnwindSafe(task)).unwrap_or(true)
+                });
+
+                state = self.lock();
+                state.tasks.append(&mut current);
+                if !state.tasks.is_empty() {
+                    state = self
+                        .wake
+                        .wait_timeout_while(state, POLL_INTERVAL, |state| !state.stopped)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0;
+                }
+            }
+        }
+
+        #[inline]
+        fn lock(&self) -> MutexGuard<'_, State> {
+            self.state.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+}
+
+// shows the path if it's known to the registry, otherwise the hash
+struct DebugPath(u64);
+
+impl fmt::Debug for DebugPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match HashRegistry::global().resolve_resource_path(self.0) {
+            Some(path) => write!(f, "{path:?}"),
+            None => write!(f, "{:#018X}", self.0),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ResourceLoadError {
+    #[error("resource failed to load")]
+    Failed,
+    #[error("resource did not finish loading in time")]
+    TimedOut,
+    #[error("resources should not be waited for on the main thread")]
+    MainThread,
 }
 
 #[derive(Debug, Error)]
@@ -143,6 +645,8 @@ pub enum ResourcePathError {
     NotCanonical,
     #[error("resource path should be valid UTF-8")]
     InvalidUnicode,
+    #[error("resource path string is not known")]
+    Unknown,
 }
 
 /// shortcut for ResRef creation.
@@ -163,9 +667,39 @@ macro_rules! res_ref {
 
 #[cfg(test)]
 mod tests {
-    use super::{encode_path, ResRef};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::mpsc::{self, TryRecvError};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::pending::{Queue, Task};
+    use super::{ResRef, ResourcePath};
     use crate::fnv1a64;
 
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    // sets the flag when the task is dropped
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::Release);
+        }
+    }
+
+    fn pending_task(dropped: &Arc<AtomicBool>) -> Task {
+        let flag = DropFlag(dropped.clone());
+        Box::new(move || {
+            let _ = &flag;
+            false
+        })
+    }
+
+    fn encode_path(path: &str) -> Result<u64, super::ResourcePathError> {
+        ResourcePath::new(path).map(ResourcePath::hash)
+    }
+
     #[test]
     fn resource_path() {
         const TOO_LONG: &str = "base\\some\\archive\\path\\that\\is\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\very\\long\\and\\above\\216\\bytes";
@@ -210,4 +744,76 @@ mod tests {
             ResRef::new(std::path::Path::new(".custom.ent")).unwrap()
         );
     }
+
+    #[test]
+    fn resource_path_helpers() {
+        const PLAYER: ResourcePath = ResourcePath::from_static("base\\characters\\player.ent");
+        assert_eq!(PLAYER.hash(), fnv1a64("base\\characters\\player.ent"));
+
+        let dir = ResourcePath::new("BASE/Characters").unwrap();
+        let player = dir.join("Player.ent").unwrap();
+        assert_eq!(player, PLAYER);
+        assert_eq!(player.extension(), Some("ent"));
+        assert_eq!(player.to_string(), "base\\characters\\player.ent");
+        assert_eq!(dir.extension(), None);
+        assert_eq!(ResourcePath::new(".gitignore").unwrap().extension(), None);
+
+        let unknown = ResourcePath::from(0x1234);
+        assert_eq!(unknown.to_string(), "0x0000000000001234");
+        assert!(unknown.join("player.ent").is_err());
+    }
+
+    #[test]
+    fn pending_queue_polls_until_done() {
+        static QUEUE: Queue = Queue::new();
+        let (tx, rx) = mpsc::channel();
+        let polls = Arc::new(AtomicUsize::new(0));
+
+        let counter = polls.clone();
+        QUEUE.schedule(Box::new(move || {
+            if counter.fetch_add(1, Ordering::AcqRel) < 2 {
+                return false;
+            }
+            tx.send(thread::current().id()).unwrap();
+            true
+        }));
+        let worker = rx.recv_timeout(TIMEOUT).unwrap();
+        assert_ne!(worker, thread::current().id());
+        assert_eq!(polls.load(Ordering::Acquire), 3);
+
+        // a task that panics is dropped without stopping the other ones
+        let (tx, rx) = mpsc::channel();
+        QUEUE.schedule(Box::new(|| panic!("task failed")));
+        QUEUE.schedule(Box::new(move || {
+            tx.send(thread::current().id()).unwrap();
+            true
+        }));
+        assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), worker);
+
+        QUEUE.stop();
+        assert_eq!(polls.load(Ordering::Acquire), 3);
+    }
+
+    #[test]
+    fn pending_queue_stop() {
+        static QUEUE: Queue = Queue::new();
+        let (tx, rx) = mpsc::channel();
+        QUEUE.schedule(Box::new(move || {
+            tx.send(()).ok();
+            false
+        }));
+        rx.recv_timeout(TIMEOUT).unwrap();
+
+        let pending = Arc::new(AtomicBool::new(false));
+        QUEUE.schedule(pending_task(&pending));
+        // the worker has exited once stop returns, so the callbacks can't run anymore
+        QUEUE.stop();
+        assert!(pending.load(Ordering::Acquire));
+        rx.try_iter().for_each(drop);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let late = Arc::new(AtomicBool::new(false));
+        QUEUE.schedule(pending_task(&late));
+        assert!(late.load(Ordering::Acquire));
+    }
 }
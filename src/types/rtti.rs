@@ -116,6 +116,34 @@ impl Type {
         str
     }
 
+    /// Initializes a value of this type at the specified memory location.
+    ///
+    /// # Safety
+    /// The memory must be valid for writes of [`Type::size`] bytes and aligned to
+    /// [`Type::alignment`].
+    #[inline]
+    pub unsafe fn construct(&self, value: ValuePtr) {
+        unsafe { (self.vft().tail.CBaseRTTIType_Construct)(&self.0, value.0) }
+    }
+
+    /// Destroys a value of this type at the specified memory location.
+    ///
+    /// # Safety
+    /// The memory must hold an initialized value of this type.
+    #[inline]
+    pub unsafe fn destruct(&self, value: ValuePtr) {
+        unsafe { (self.vft().tail.CBaseRTTIType_Destruct)(&self.0, value.0) }
+    }
+
+    /// Assigns a copy of the value at `rhs` to the value at `lhs`.
+    ///
+    /// # Safety
+    /// Both locations must hold initialized values of this type.
+    #[inline]
+    pub unsafe fn assign(&self, lhs: ValuePtr, rhs: ValuePtr) {
+        unsafe { (self.vft().tail.CBaseRTTIType_Assign)(&self.0, lhs.0, rhs.0) }
+    }
+
     #[inline]
     fn vft(&self) -> &TypeVft {
         unsafe { &*(self.0.vtable_.cast::<TypeVft>()) }
@@ -773,6 +801,11 @@ impl Function {
         CName::from_raw(self.0.fullName)
     }
 
+    #[inline]
+    pub fn short_name(&self) -> CName {
+        CName::from_raw(self.0.shortName)
+    }
+
     #[inline]
     pub fn flags(&self) -> FunctionFlags {
         FunctionFlags(self.0.flags)
@@ -803,6 +836,22 @@ impl Function {
         unsafe { &*(self.0.returnType.cast::<Property>()) }
     }
 
+    /// Returns the bytecode of the function. Native functions have no bytecode.
+    #[inline]
+    pub fn bytecode(&self) -> &[u8] {
+        let buffer = &self.0.bytecode.bytecode;
+        if buffer.data.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(buffer.data.cast::<u8>(), buffer.size as usize) }
+    }
+
+    /// Returns the return type of the function, or `None` if the function returns nothing.
+    #[inline]
+    pub fn return_type(&self) -> Option<&Type> {
+        unsafe { self.0.returnType.cast::<Property>().as_ref() }.map(Property::type_)
+    }
+
     #[inline]
     pub fn add_param(&mut self, typ: CName, name: &CStr, is_out: bool, is_optional: bool) -> bool {
         unsafe {
@@ -816,6 +865,22 @@ impl Function {
         unsafe { self.0.SetReturnType(typ.to_raw()) };
     }
 
+    /// Returns a pointer to the slot holding the native handler of this function, or `None` if
+    /// the function is not native.
+    pub(crate) fn native_handler_ptr(&self) -> Option<*mut VoidPtr> {
+        if !self.flags().is_native() {
+            return None;
+        }
+        let ptr = if self.parent().is_some() {
+            let func = self as *const _ as *mut red::CClassFunction;
+            unsafe { ptr::addr_of_mut!((*func).func).cast::<VoidPtr>() }
+        } else {
+            let func = self as *const _ as *mut red::CGlobalFunction;
+            unsafe { ptr::addr_of_mut!((*func).func).cast::<VoidPtr>() }
+        };
+        Some(ptr)
+    }
+
     pub fn execute<A, R>(&self, ctx: Option<&IScriptable>, mut args: A) -> Result<R, InvokeError>
     where
         A: Args,
@@ -835,7 +900,7 @@ impl Function {
             return Err(err);
         }
 
-        self.execute_internal(ctx, arr.as_ref(), &mut out)?;
+        self.execute_internal(ctx, arr.as_ref(), Some(&mut out))?;
         Ok(R::from_repr(ret))
     }
 
@@ -873,18 +938,18 @@ impl Function {
         Ok(())
     }
 
-    fn execute_internal(
+    pub(crate) fn execute_internal(
         &self,
         ctx: Option<&IScriptable>,
         args: &[StackArg<'_>],
-        ret: &mut StackArg<'_>,
+        ret: Option<&mut StackArg<'_>>,
     ) -> Result<(), InvokeError> {
         let success = unsafe {
             let mut stack = red::CStack::new(
                 mem::transmute::<Option<&IScriptable>, VoidPtr>(ctx),
                 mem::transmute::<*const StackArg<'_>, *mut red::CStackType>(args.as_ptr()),
                 args.len() as u32,
-                ret.as_raw_mut(),
+                ret.map_or(ptr::null_mut(), |ret| ret.as_raw_mut() as *mut _),
             );
             red::CBaseFunction_Execute(&self.0 as *const _ as *mut red::CBaseFunction, &mut stack)
         };
@@ -1218,6 +1283,22 @@ impl PropertyFlags {
     pub fn set_in_value_holder(&mut self, in_value_holder: bool) {
         self.0.set_inValueHolder(in_value_holder as u64)
     }
+
+    pub fn is_out(&self) -> bool {
+        self.0.isOut() != 0
+    }
+
+    pub fn set_is_out(&mut self, is_out: bool) {
+        self.0.set_isOut(is_out as u64)
+    }
+
+    pub fn is_optional(&self) -> bool {
+        self.0.isOptional() != 0
+    }
+
+    pub fn set_is_optional(&mut self, is_optional: bool) {
+        self.0.set_isOptional(is_optional as u64)
+    }
 }
 
 #[derive(Debug)]
@@ -1443,6 +1524,16 @@ impl ValueContainer {
 pub struct ValuePtr(VoidPtr);
 
 impl ValuePtr {
+    #[inline]
+    pub(crate) fn new(ptr: VoidPtr) -> Self {
+        Self(ptr)
+    }
+
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> VoidPtr {
+        self.0
+    }
+
     pub unsafe fn unwrap_ref(&self) -> Option<&IScriptable> {
         let ptr = self.0 as *mut red::SharedPtrBase<red::IScriptable>;
         let inst = (*ptr).instance;
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::iter::FusedIterator;
+
+use super::hash::{self, Hash};
+use super::RedHashMap;
+
+/// A hash set compatible with the one used by the game. It shares the layout of a
+/// [`RedHashMap`] with no values.
+#[repr(transparent)]
+pub struct RedHashSet<T>(RedHashMap<T, ()>);
+
+impl<T> RedHashSet<T> {
+    /// Creates a new empty set that allocates from the engine pool.
+    #[inline]
+    pub fn new() -> Self {
+        Self(RedHashMap::new())
+    }
+
+    /// Creates a new empty set with space for at least `capacity` elements.
+    #[inline]
+    pub fn with_capacity(capacity: u32) -> Self {
+        Self(RedHashMap::with_capacity(capacity))
+    }
+
+    /// Adds a value to the set. Returns `false` if the value was already present.
+    #[inline]
+    pub fn insert(&mut self, value: T) -> bool
+    where
+        T: Hash + PartialEq,
+    {
+        self.0.insert(value, ()).is_none()
+    }
+
+    /// Removes a value from the set. Returns `true` if the value was present.
+    #[inline]
+    pub fn remove(&mut self, value: &T) -> bool
+    where
+        T: Hash + PartialEq,
+    {
+        self.0.remove(value).is_some()
+    }
+
+    /// Removes and returns the value in the set equal to the given one, if any.
+    #[inline]
+    pub fn take(&mut self, value: &T) -> Option<T>
+    where
+        T: Hash + PartialEq,
+    {
+        self.0.remove_entry(value).map(|(value, ())| value)
+    }
+
+    /// Returns `true` if the set contains the value.
+    #[inline]
+    pub fn contains(&self, value: &T) -> bool
+    where
+        T: Hash + PartialEq,
+    {
+        self.0.contains_key(value)
+    }
+
+    /// Removes all elements from the set, keeping the allocated memory.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns an iterator over the elements of the set.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter(self.0.keys())
+    }
+
+    /// Returns the number of elements in the set.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.0.len()
+    }
+
+    /// Returns `true` if the set contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RedHashSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T> Default for RedHashSet<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a RedHashSet<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Hash + PartialEq> FromIterator<T> for RedHashSet<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Hash + PartialEq> Extend<T> for RedHashSet<T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.0.extend(iter.into_iter().map(|value| (value, ())));
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'a, T>(hash::Keys<'a, T, ()>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::RedHashSet;
+    use crate::types::allocator::mock;
+
+    #[test]
+    fn insert_remove_contains() {
+        mock::install();
+        let mut set: RedHashSet<u64> = [1, 2, 3, 2].into_iter().collect();
+        assert_eq!(set.len(), 3);
+        assert!(!set.insert(1));
+        assert!(set.insert(4));
+        assert!(set.remove(&2));
+        assert!(!set.remove(&2));
+        assert!(set.contains(&3));
+        assert_eq!(set.take(&4), Some(4));
+
+        let mut values: Vec<_> = set.iter().copied().collect();
+        values.sort_unstable();
+        assert_eq!(values, [1, 3]);
+
+        set.clear();
+        assert!(set.is_empty());
+    }
+}
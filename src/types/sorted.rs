@@ -0,0 +1,459 @@
+use std::{fmt, iter, ops, slice};
+
+use super::RedArray;
+
+/// An array that keeps its elements sorted. Duplicates are allowed.
+#[repr(transparent)]
+pub struct SortedArray<T>(RedArray<T>);
+
+impl<T> SortedArray<T> {
+    /// Creates a new empty [`SortedArray`].
+    #[inline]
+    pub const fn new() -> Self {
+        Self(RedArray::new())
+    }
+
+    /// Creates a new empty [`SortedArray`] with the specified capacity.
+    #[inline]
+    pub fn with_capacity(capacity: u32) -> Self {
+        Self(RedArray::with_capacity(capacity))
+    }
+
+    /// Returns the number of elements in the array.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.0.len()
+    }
+
+    /// Returns `true` if the array contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Inserts an element at its sorted position, after any elements equal to it.
+    pub fn insert(&mut self, value: T)
+    where
+        T: Ord,
+    {
+        let index = self.0.partition_point(|elem| elem <= &value);
+        self.0.insert(index as u32, value);
+    }
+
+    /// Removes one element equal to `value`. Returns `true` if an element was removed.
+    pub fn remove(&mut self, value: &T) -> bool
+    where
+        T: Ord,
+    {
+        match self.0.binary_search(value) {
+            Ok(index) => {
+                self.0.remove(index as u32);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Removes and returns the element at position `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn remove_at(&mut self, index: u32) -> T {
+        self.0.remove(index)
+    }
+
+    /// Returns `true` if the array contains an element equal to `value`.
+    #[inline]
+    pub fn contains(&self, value: &T) -> bool
+    where
+        T: Ord,
+    {
+        self.0.binary_search(value).is_ok()
+    }
+
+    /// Clears the array, removing all values.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns an iterator over the elements in ascending order.
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.0.iter()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SortedArray<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> Default for SortedArray<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// only shared access is allowed, mutating the elements could break the ordering
+impl<T> ops::Deref for SortedArray<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SortedArray<T> {
+    type IntoIter = slice::Iter<'a, T>;
+    type Item = &'a T;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SortedArray<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut array: RedArray<T> = iter.into_iter().collect();
+        array.sort();
+        Self(array)
+    }
+}
+
+impl<T: Ord> Extend<T> for SortedArray<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+/// An ordered set, stored as a sorted array of unique elements.
+#[repr(transparent)]
+pub struct RedSet<T>(SortedArray<T>);
+
+impl<T> RedSet<T> {
+    /// Creates a new empty [`RedSet`].
+    #[inline]
+    pub const fn new() -> Self {
+        Self(SortedArray::new())
+    }
+
+    /// Returns the number of elements in the set.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.0.len()
+    }
+
+    /// Returns `true` if the set contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds a value to the set. Returns `false` if the value was already present.
+    pub fn insert(&mut self, value: T) -> bool
+    where
+        T: Ord,
+    {
+        match self.0 .0.binary_search(&value) {
+            Ok(_) => false,
+            Err(index) => {
+                self.0 .0.insert(index as u32, value);
+                true
+            }
+        }
+    }
+
+    /// Removes a value from the set. Returns `true` if the value was present.
+    #[inline]
+    pub fn remove(&mut self, value: &T) -> bool
+    where
+        T: Ord,
+    {
+        self.0.remove(value)
+    }
+
+    /// Returns `true` if the set contains the value.
+    #[inline]
+    pub fn contains(&self, value: &T) -> bool
+    where
+        T: Ord,
+    {
+        self.0.contains(value)
+    }
+
+    /// Clears the set, removing all values.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns an iterator over the elements in ascending order.
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.0.iter()
+    }
+
+    /// Returns the elements as a sorted slice.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RedSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T> Default for RedSet<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a RedSet<T> {
+    type IntoIter = slice::Iter<'a, T>;
+    type Item = &'a T;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for RedSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut array: RedArray<T> = iter.into_iter().collect();
+        array.sort();
+        array.dedup();
+        Self(SortedArray(array))
+    }
+}
+
+impl<T: Ord> Extend<T> for RedSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+/// An ordered map, stored as a sorted array of unique keys and an array of values at matching
+/// positions.
+#[repr(C)]
+pub struct RedMap<K, V> {
+    keys: SortedArray<K>,
+    values: RedArray<V>,
+}
+
+impl<K, V> RedMap<K, V> {
+    /// Creates a new empty [`RedMap`].
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            keys: SortedArray::new(),
+            values: RedArray::new(),
+        }
+    }
+
+    /// Returns the number of entries in the map.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.keys.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<&V>
+    where
+        K: Ord,
+    {
+        let index = self.keys.binary_search(key).ok()?;
+        self.values.get(index)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    #[inline]
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V>
+    where
+        K: Ord,
+    {
+        let index = self.keys.binary_search(key).ok()?;
+        self.values.get_mut(index)
+    }
+
+    /// Returns `true` if the map contains a value for the key.
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool
+    where
+        K: Ord,
+    {
+        self.keys.contains(key)
+    }
+
+    /// Inserts a key-value pair into the map. If the key was already present, the value is
+    /// replaced and the old one is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    where
+        K: Ord,
+    {
+        match self.keys.binary_search(&key) {
+            Ok(index) => Some(std::mem::replace(&mut self.values[index], value)),
+            Err(index) => {
+                self.keys.0.insert(index as u32, key);
+                self.values.insert(index as u32, value);
+                None
+            }
+        }
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    pub fn remove(&mut self, key: &K) -> Option<V>
+    where
+        K: Ord,
+    {
+        let index = self.keys.binary_search(key).ok()? as u32;
+        self.keys.remove_at(index);
+        Some(self.values.remove(index))
+    }
+
+    /// Clears the map, removing all entries.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.values.clear();
+    }
+
+    /// Returns an iterator over the entries of the map in ascending key order.
+    #[inline]
+    pub fn iter(&self) -> iter::Zip<slice::Iter<'_, K>, slice::Iter<'_, V>> {
+        self.keys.iter().zip(self.values.iter())
+    }
+
+    /// Returns an iterator over the entries of the map with mutable references to the values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> iter::Zip<slice::Iter<'_, K>, slice::IterMut<'_, V>> {
+        self.keys.iter().zip(self.values.iter_mut())
+    }
+
+    /// Returns the keys of the map as a sorted slice.
+    #[inline]
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    /// Returns the values of the map in the order of their keys.
+    #[inline]
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+
+    /// Returns the values of the map in the order of their keys.
+    #[inline]
+    pub fn values_mut(&mut self) -> &mut [V] {
+        &mut self.values
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for RedMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> Default for RedMap<K, V> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a RedMap<K, V> {
+    type IntoIter = iter::Zip<slice::Iter<'a, K>, slice::Iter<'a, V>>;
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for RedMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for RedMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RedMap, RedSet, SortedArray};
+    use crate::types::allocator::mock;
+
+    #[test]
+    fn sorted_array() {
+        mock::install();
+        let mut arr: SortedArray<i32> = [5, 1, 3].into_iter().collect();
+        arr.insert(2);
+        arr.insert(3);
+        arr.extend([0, 6]);
+        assert_eq!(&*arr, &[0, 1, 2, 3, 3, 5, 6]);
+
+        assert!(arr.remove(&3));
+        assert!(!arr.remove(&4));
+        assert!(arr.contains(&3));
+        assert_eq!(&*arr, &[0, 1, 2, 3, 5, 6]);
+    }
+
+    #[test]
+    fn set() {
+        mock::install();
+        let mut set: RedSet<u32> = [3, 1, 3, 2].into_iter().collect();
+        assert_eq!(set.as_slice(), &[1, 2, 3]);
+        assert!(set.insert(0));
+        assert!(!set.insert(2));
+        assert!(set.remove(&1));
+        assert!(!set.contains(&1));
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), [0, 2, 3]);
+    }
+
+    #[test]
+    fn map() {
+        mock::install();
+        let mut map: RedMap<u32, &str> = [(2, "b"), (1, "a")].into_iter().collect();
+        assert_eq!(map.insert(3, "c"), None);
+        assert_eq!(map.insert(1, "x"), Some("a"));
+        assert_eq!(map.get(&1), Some(&"x"));
+        assert_eq!(map.keys(), &[1, 2, 3]);
+        assert_eq!(map.values(), &["x", "b", "c"]);
+
+        assert_eq!(map.remove(&2), Some("b"));
+        assert_eq!(map.remove(&2), None);
+        assert_eq!(map.iter().collect::<Vec<_>>(), [(&1, &"x"), (&3, &"c")]);
+    }
+}
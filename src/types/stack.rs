@@ -1,7 +1,11 @@
 use std::marker::PhantomData;
 use std::{iter, ptr};
 
-use super::{CName, Function, IScriptable, Instr, Type, ValueContainer, OPCODE_SIZE};
+use super::bytecode::{self, Decoder, Local, ObjectField, Param};
+use super::{
+    CName, DecodeError, Function, IScriptable, Instr, Instruction, Type, ValueContainer,
+    OPCODE_SIZE,
+};
 use crate::raw::root::RED4ext as red;
 use crate::repr::NativeRepr;
 use crate::systems::RttiSystem;
@@ -55,6 +59,21 @@ impl StackFrame {
         ValueContainer::new(self.0.params)
     }
 
+    /// Returns the offset of the current position in the bytecode of the function.
+    #[inline]
+    pub fn code_offset(&self) -> Option<usize> {
+        let code = self.func().bytecode();
+        let offset = (self.0.code as usize).checked_sub(code.as_ptr() as usize)?;
+        (offset <= code.len()).then_some(offset)
+    }
+
+    /// Decodes the instruction at the current position of the stack frame.
+    /// Returns `None` if the frame has no code or it has reached the end of it.
+    #[inline]
+    pub fn instr(&self) -> Option<Result<Instruction<'_>, DecodeError>> {
+        Decoder::at(self.func().bytecode(), self.code_offset()?).next()
+    }
+
     /// Interprets the code at specified offset as an instruction of type `I`.
     pub unsafe fn instr_at<I: Instr>(&self, offset: isize) -> Option<&I> {
         if self.0.code.is_null() {
@@ -70,6 +89,42 @@ impl StackFrame {
         self.0.code = unsafe { self.0.code.offset(OPCODE_SIZE) };
     }
 
+    /// Moves the frame to the param end instruction that terminates the arguments of the current
+    /// call, skipping any arguments that haven't been read. Returns `false` if the frame has no
+    /// code or the end of the arguments could not be found.
+    pub(crate) unsafe fn skip_to_param_end(&mut self) -> bool {
+        if !self.has_code() {
+            return false;
+        }
+        let Some(offset) = self.code_offset() else {
+            return false;
+        };
+        match bytecode::param_end_offset(self.func().bytecode(), offset) {
+            Some(end) => {
+                unsafe { self.skip((end - offset) as isize) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Steps over an arbitrary number of bytes.
+    #[inline]
+    pub(crate) unsafe fn skip(&mut self, bytes: isize) {
+        self.0.code = unsafe { self.0.code.offset(bytes) };
+    }
+
+    /// Evaluates the arguments of a call into the provided locations and steps over the
+    /// terminating param end opcode.
+    pub(crate) unsafe fn read_call_args(&mut self, args: impl IntoIterator<Item = VoidPtr>) {
+        let current = self.0.currentParam;
+        for ptr in args {
+            unsafe { self.read_arg(ptr) };
+        }
+        self.0.currentParam = current;
+        unsafe { self.step() };
+    }
+
     /// Retrieves the next argument from the stack frame.
     ///
     /// # Safety
@@ -81,7 +136,27 @@ impl StackFrame {
         out
     }
 
-    unsafe fn read_arg(&mut self, ptr: VoidPtr) {
+    /// Retrieves a reference to the next argument. Arguments that refer to variables are
+    /// borrowed directly, other expressions are evaluated into `storage`.
+    ///
+    /// # Safety
+    /// The type `T` must be the correct type of the next argument.
+    pub(crate) unsafe fn borrow_arg<'a, T: Default>(
+        &mut self,
+        storage: &'a mut Option<T>,
+    ) -> &'a T {
+        let opcode = unsafe { *self.0.code } as u8;
+        if matches!(opcode, Local::OPCODE | Param::OPCODE | ObjectField::OPCODE) {
+            // with no output these instructions only point the frame at the variable, the panic
+            // is reported by the exported function along with the script backtrace
+            unsafe { self.read_arg(ptr::null_mut()) };
+            let data = unsafe { self.0.data.cast::<T>().as_ref() };
+            return data.expect("the game should point the frame at the argument variable");
+        }
+        storage.insert(unsafe { self.get_arg() })
+    }
+
+    pub(crate) unsafe fn read_arg(&mut self, ptr: VoidPtr) {
         self.0.data = ptr::null_mut();
         self.0.dataType = ptr::null_mut();
         self.0.currentParam += 1;
@@ -110,6 +185,16 @@ impl<'a> StackArg<'a> {
         Some(Self(inner, PhantomData))
     }
 
+    /// Creates a new stack argument from a type and a pointer to a value of that type.
+    #[inline]
+    pub(crate) fn from_raw_parts(type_: &Type, value: VoidPtr) -> Self {
+        let inner = red::CStackType {
+            type_: type_.as_raw() as *const _ as *mut red::CBaseRTTIType,
+            value,
+        };
+        Self(inner, PhantomData)
+    }
+
     /// Returns the type of the stack argument.
     #[inline]
     pub fn type_(&self) -> Option<&'static Type> {
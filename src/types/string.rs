@@ -1,9 +1,23 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::Infallible;
 use std::ffi::{CStr, CString};
-use std::{fmt, ops, ptr};
+use std::str::FromStr;
+use std::{fmt, hash, ops, ptr, slice, str};
 
 use crate::raw::root::RED4ext as red;
 
-/// A dynamically allocated string.
+// strings that don't fit the inline buffer are allocated on the heap, which is signalled by
+// a flag stored in the upper bits of the length
+const HEAP_FLAG: u32 = 0x40000000;
+const LENGTH_MASK: u32 = 0x3FFFFFFF;
+// the inline buffer is 0x14 bytes long and has to fit the null terminator
+const INLINE_CAPACITY: usize = 0x14 - 1;
+
+/// A dynamically allocated string. Short strings are stored inline without allocating.
+///
+/// The string is not required to be valid UTF-8, so the bytes are preserved as they are and
+/// validation only happens when a `&str` is requested.
 #[repr(transparent)]
 pub struct RedString(red::CString);
 
@@ -13,6 +27,96 @@ impl RedString {
     pub fn new() -> Self {
         Self(unsafe { red::CString::new(ptr::null_mut()) })
     }
+
+    /// Returns the length of the string in bytes, excluding the null terminator.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.0.length & LENGTH_MASK
+    }
+
+    /// Returns `true` if the string is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the string is stored in the inline buffer.
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.0.length < HEAP_FLAG
+    }
+
+    /// Returns the bytes of the string, excluding the null terminator.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.0.c_str().cast(), self.len() as usize) }
+    }
+
+    /// Returns the string as a `&str` if it's valid UTF-8.
+    #[inline]
+    pub fn as_str(&self) -> Result<&str, str::Utf8Error> {
+        str::from_utf8(self.as_bytes())
+    }
+
+    /// Returns the string as a `&str`, replacing invalid UTF-8 sequences with
+    /// [`U+FFFD REPLACEMENT CHARACTER`](char::REPLACEMENT_CHARACTER). It only allocates
+    /// if the string is not valid UTF-8.
+    #[inline]
+    pub fn as_str_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.as_bytes())
+    }
+
+    /// Converts the string into a [`String`] if it's valid UTF-8, otherwise returns
+    /// the original string.
+    pub fn into_string(self) -> Result<String, Self> {
+        match self.as_str() {
+            Ok(str) => Ok(str.to_owned()),
+            Err(_) => Err(self),
+        }
+    }
+
+    /// Appends a string slice to the end of this string. The slice is truncated at the first
+    /// null byte, if any.
+    #[inline]
+    pub fn push_str(&mut self, str: &str) {
+        self.push_bytes(str.as_bytes());
+    }
+
+    /// Appends a character to the end of this string.
+    #[inline]
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Appends raw bytes to the end of this string. The bytes are truncated at the first
+    /// null byte, if any.
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        let bytes = bytes
+            .iter()
+            .position(|&b| b == 0)
+            .map_or(bytes, |end| &bytes[..end]);
+        if bytes.is_empty() {
+            return;
+        }
+
+        let len = self.len() as usize;
+        let new_len = len + bytes.len();
+        if self.is_inline() && new_len <= INLINE_CAPACITY {
+            unsafe {
+                // the inline buffer is at the start of the string
+                let buf = ptr::addr_of_mut!(self.0).cast::<u8>();
+                buf.add(len)
+                    .copy_from_nonoverlapping(bytes.as_ptr(), bytes.len());
+                buf.add(new_len).write(0);
+            }
+            self.0.length = new_len as u32;
+        } else {
+            let mut buf = Vec::with_capacity(new_len + 1);
+            buf.extend_from_slice(self.as_bytes());
+            buf.extend_from_slice(bytes);
+            *self = Self::from(unsafe { CString::from_vec_unchecked(buf) });
+        }
+    }
 }
 
 impl Default for RedString {
@@ -25,7 +129,7 @@ impl Default for RedString {
 impl Clone for RedString {
     #[inline]
     fn clone(&self) -> Self {
-        Self::from(self.as_ref())
+        Self::from(self.as_bytes())
     }
 }
 
@@ -52,17 +156,48 @@ impl From<CString> for RedString {
     }
 }
 
+impl From<&[u8]> for RedString {
+    /// Creates a string from raw bytes, truncated at the first null byte. Strings that fit
+    /// the inline buffer are created without allocating.
+    #[inline]
+    fn from(value: &[u8]) -> Self {
+        let mut str = Self::new();
+        str.push_bytes(value);
+        str
+    }
+}
+
+impl From<&str> for RedString {
+    #[inline]
+    fn from(value: &str) -> Self {
+        Self::from(value.as_bytes())
+    }
+}
+
 impl From<String> for RedString {
     #[inline]
     fn from(value: String) -> Self {
-        RedString::from(crate::truncated_cstring(value))
+        if value.len() <= INLINE_CAPACITY {
+            Self::from(value.as_str())
+        } else {
+            Self::from(crate::truncated_cstring(value))
+        }
     }
 }
 
 impl From<RedString> for String {
     #[inline]
     fn from(value: RedString) -> Self {
-        value.to_string_lossy().into_owned()
+        value.as_str_lossy().into_owned()
+    }
+}
+
+impl FromStr for RedString {
+    type Err = Infallible;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(s))
     }
 }
 
@@ -73,23 +208,149 @@ impl AsRef<CStr> for RedString {
     }
 }
 
+impl AsRef<[u8]> for RedString {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl PartialEq for RedString {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for RedString {}
+
+impl PartialEq<str> for RedString {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for RedString {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<String> for RedString {
+    #[inline]
+    fn eq(&self, other: &String) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<RedString> for str {
+    #[inline]
+    fn eq(&self, other: &RedString) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<RedString> for &str {
+    #[inline]
+    fn eq(&self, other: &RedString) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialOrd for RedString {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RedString {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl hash::Hash for RedString {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
+impl fmt::Write for RedString {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.push(c);
+        Ok(())
+    }
+}
+
 impl fmt::Debug for RedString {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self.as_ref())
+        write!(f, "{:?}", &**self)
     }
 }
 
 impl fmt::Display for RedString {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_string_lossy())
+        f.write_str(&self.as_str_lossy())
     }
 }
 
 impl Drop for RedString {
     #[inline]
     fn drop(&mut self) {
-        unsafe { self.0.destruct() }
+        // inline strings don't own any memory
+        if !self.is_inline() {
+            unsafe { self.0.destruct() }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RedString, INLINE_CAPACITY};
+
+    #[test]
+    fn push_within_inline_capacity() {
+        let mut str = RedString::new();
+        str.push_str("0123456789");
+        str.push_bytes(b"abcdefghi");
+        assert_eq!(str.len() as usize, INLINE_CAPACITY);
+        assert!(str.is_inline());
+        assert_eq!(str, "0123456789abcdefghi");
+        assert_eq!(str.to_bytes_with_nul().last(), Some(&0));
+    }
+
+    #[test]
+    #[ignore = "heap strings are allocated by the game"]
+    fn push_past_inline_capacity() {
+        let mut str = RedString::from("0123456789abcdefghi");
+        str.push('j');
+        assert_eq!(str.len() as usize, INLINE_CAPACITY + 1);
+        assert!(!str.is_inline());
+        assert_eq!(str, "0123456789abcdefghij");
+    }
+
+    #[test]
+    fn push_truncates_at_nul() {
+        let mut str = RedString::from(&b"abc\0def"[..]);
+        assert_eq!(str, "abc");
+        str.push_bytes(b"\0ghi");
+        assert_eq!(str, "abc");
+        str.push_str("de\0f");
+        assert_eq!(str, "abcde");
+        assert_eq!(str.len(), 5);
     }
 }
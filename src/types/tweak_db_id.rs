@@ -1,8 +1,11 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::hash::Hash;
+use std::str::FromStr;
 
 use const_crc32::{crc32, crc32_seed};
+use thiserror::Error;
 
+use super::HashRegistry;
 use crate::raw::root::RED4ext as red;
 
 #[derive(Default, Clone, Copy)]
@@ -65,27 +68,89 @@ impl TweakDbId {
         )
     }
 
-    pub(super) const fn hash(&self) -> u32 {
+    /// Returns the CRC32 hash of the name the id was created from.
+    #[inline]
+    pub const fn hash(&self) -> u32 {
         unsafe { self.0.__bindgen_anon_1.name }.hash
     }
 
-    pub(super) const fn len(&self) -> u8 {
+    /// Returns the length of the name the id was created from.
+    #[inline]
+    pub const fn len(&self) -> u8 {
         unsafe { self.0.__bindgen_anon_1.name }.length
     }
 
-    pub(super) const fn to_inner(self) -> red::TweakDBID {
+    /// Returns `true` if the id was created from an empty name.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) const fn to_inner(self) -> red::TweakDBID {
         self.0
     }
 }
 
 impl Debug for TweakDbId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("TweakDbId")
-            .field(&unsafe { self.0.__bindgen_anon_1.value })
-            .finish()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match HashRegistry::global().resolve_tweak_db_id(*self) {
+            Some(name) => f.debug_tuple("TweakDbId").field(&name).finish(),
+            None => write!(f, "TweakDbId({self})"),
+        }
+    }
+}
+
+/// Prints the name of the id if it's known to the [`HashRegistry`], otherwise prints the hash
+/// and the length in the `<TDBID:XXXXXXXX:YY>` format.
+impl fmt::Display for TweakDbId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match HashRegistry::global().resolve_tweak_db_id(*self) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "<TDBID:{:08X}:{:02X}>", self.hash(), self.len()),
+        }
+    }
+}
+
+/// Parses an id either from its name, e.g. `Items.FirstAidWhiffV0`, or from the
+/// `<TDBID:XXXXXXXX:YY>` format. Parsed names are added to the [`HashRegistry`].
+impl FromStr for TweakDbId {
+    type Err = TweakDbIdParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(inner) = s.strip_prefix("<TDBID:").and_then(|s| s.strip_suffix('>')) {
+            let (hash, len) = inner
+                .split_once(':')
+                .filter(|(hash, len)| hash.len() == 8 && len.len() == 2)
+                .ok_or(TweakDbIdParseError::InvalidFormat)?;
+            if !hash
+                .bytes()
+                .chain(len.bytes())
+                .all(|b| b.is_ascii_hexdigit())
+            {
+                return Err(TweakDbIdParseError::InvalidDigit);
+            }
+            let hash =
+                u32::from_str_radix(hash, 16).map_err(|_| TweakDbIdParseError::InvalidDigit)?;
+            let len = u8::from_str_radix(len, 16).map_err(|_| TweakDbIdParseError::InvalidDigit)?;
+            return Ok(Self::new_with(hash, len, [0, 0, 0]));
+        }
+        if s.len() > u8::MAX as usize {
+            return Err(TweakDbIdParseError::TooLong);
+        }
+        Ok(HashRegistry::global().register_tweak_db_id(s))
     }
 }
 
+#[derive(Debug, Error)]
+pub enum TweakDbIdParseError {
+    #[error("TweakDBID should be in the <TDBID:XXXXXXXX:YY> format")]
+    InvalidFormat,
+    #[error("TweakDBID should only contain hexadecimal digits")]
+    InvalidDigit,
+    #[error("TweakDBID name should be at most 255 bytes long")]
+    TooLong,
+}
+
 impl PartialEq for TweakDbId {
     fn eq(&self, other: &Self) -> bool {
         u64::from(*self).eq(&u64::from(*other))
@@ -126,6 +191,23 @@ impl Hash for TweakDbId {
     }
 }
 
+/// Creates a [`TweakDbId`](crate::types::TweakDbId) from a string literal at compile time.
+///
+/// # Example
+/// ```rust
+/// use red4rs::tdbid;
+/// use red4rs::types::TweakDbId;
+///
+/// const ITEM: TweakDbId = tdbid!("Items.FirstAidWhiffV0");
+/// ```
+#[macro_export]
+macro_rules! tdbid {
+    ($lit:literal) => {{
+        const ID: $crate::types::TweakDbId = $crate::types::TweakDbId::new($lit);
+        ID
+    }};
+}
+
 #[cfg(test)]
 mod tests {
     use super::TweakDbId;
@@ -149,4 +231,26 @@ mod tests {
         assert_eq!(original.tdb_offset(), 0);
         assert_eq!(modified.tdb_offset(), 128);
     }
+
+    #[test]
+    fn formatting() {
+        let unknown = TweakDbId::new("Items.UnknownToTheRegistry");
+        let str = unknown.to_string();
+        assert_eq!(str, format!("<TDBID:{:08X}:1A>", unknown.hash()));
+        assert_eq!(str.parse::<TweakDbId>().unwrap(), unknown);
+
+        let id = TweakDbId::from(90_628_141_458);
+        assert_eq!("<TDBID:19DBB192:15>".parse::<TweakDbId>().unwrap(), id);
+        assert_eq!(id.hash(), 0x19DB_B192);
+        assert_eq!(id.len(), 21);
+
+        let parsed = "Items.FirstAidWhiffV0".parse::<TweakDbId>().unwrap();
+        assert_eq!(parsed, id);
+        assert_eq!(parsed.to_string(), "Items.FirstAidWhiffV0");
+        assert_eq!(crate::tdbid!("Items.FirstAidWhiffV0"), id);
+
+        assert!("<TDBID:19DBB192>".parse::<TweakDbId>().is_err());
+        assert!("<TDBID:19DBB19G:15>".parse::<TweakDbId>().is_err());
+        assert!("a".repeat(256).parse::<TweakDbId>().is_err());
+    }
 }
@@ -0,0 +1,142 @@
+use std::{fmt, ptr};
+
+use super::{CName, Type, ValuePtr};
+use crate::raw::root::RED4ext as red;
+use crate::repr::NativeRepr;
+use crate::systems::RttiSystem;
+use crate::VoidPtr;
+
+// the lowest bit of the type pointer is set when the value is stored inline
+const INLINE_FLAG: usize = 1;
+const INLINE_SIZE: u32 = 8;
+const INLINE_ALIGNMENT: u32 = 8;
+
+/// A value of any type along with its RTTI type. Small values are stored inline, while
+/// larger ones are allocated with the allocator of their type.
+#[repr(transparent)]
+pub struct Variant(red::Variant);
+
+impl Variant {
+    /// Creates a new variant holding the specified value. Returns `None` if the type of the
+    /// value could not be resolved.
+    pub fn new<T: NativeRepr>(value: T) -> Option<Self> {
+        let rtti = RttiSystem::get();
+        let type_ = rtti.get_type(CName::new(T::NAME))?;
+        let mut this = Self::default();
+        unsafe {
+            let data = this.init(type_);
+            data.as_ptr().cast::<T>().write(value);
+        }
+        Some(this)
+    }
+
+    /// Creates a new empty variant.
+    #[inline]
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Returns the type of the value held by the variant, if it's not empty.
+    #[inline]
+    pub fn type_(&self) -> Option<&Type> {
+        let ptr = self.0.type_ as usize & !INLINE_FLAG;
+        unsafe { (ptr as *const Type).as_ref() }
+    }
+
+    /// Returns `true` if the variant does not hold a value.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.type_().is_none()
+    }
+
+    /// Returns a reference to the value held by the variant if it's of type `T`.
+    pub fn try_get<T: NativeRepr>(&self) -> Option<&T> {
+        let type_ = self.type_()?;
+        if type_.name() != CName::new(T::NAME) {
+            return None;
+        }
+        Some(unsafe { &*self.data_ptr().as_ptr().cast::<T>() })
+    }
+
+    #[inline]
+    fn is_inline(&self) -> bool {
+        self.0.type_ as usize & INLINE_FLAG != 0
+    }
+
+    #[inline]
+    fn data_ptr(&self) -> ValuePtr {
+        if self.is_inline() {
+            ValuePtr::new(unsafe { self.0.__bindgen_anon_1.inlined.as_ptr() } as VoidPtr)
+        } else {
+            ValuePtr::new(unsafe { self.0.__bindgen_anon_1.instance })
+        }
+    }
+
+    // sets the type and returns uninitialized storage for a value of that type,
+    // the variant must be empty
+    unsafe fn init(&mut self, type_: &Type) -> ValuePtr {
+        let raw_type = type_.as_raw() as *const red::CBaseRTTIType;
+        if type_.size() <= INLINE_SIZE && type_.alignment() <= INLINE_ALIGNMENT {
+            self.0.type_ = (raw_type as usize | INLINE_FLAG) as *const red::CBaseRTTIType;
+        } else {
+            let instance = unsafe {
+                type_
+                    .allocator()
+                    .alloc_aligned(type_.size(), type_.alignment())
+            };
+            assert!(!instance.is_null(), "variant allocation should not fail");
+            self.0.type_ = raw_type;
+            self.0.__bindgen_anon_1.instance = instance;
+        }
+        self.data_ptr()
+    }
+}
+
+impl Default for Variant {
+    #[inline]
+    fn default() -> Self {
+        Self(red::Variant::default())
+    }
+}
+
+impl Clone for Variant {
+    fn clone(&self) -> Self {
+        let mut copy = Self::default();
+        if let Some(type_) = self.type_() {
+            unsafe {
+                let data = copy.init(type_);
+                type_.construct(data);
+                type_.assign(data, self.data_ptr());
+            }
+        }
+        copy
+    }
+}
+
+impl fmt::Debug for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.type_() {
+            Some(type_) => {
+                let value = unsafe { type_.to_string(self.data_ptr()) };
+                write!(f, "Variant({}: {})", type_.name(), value)
+            }
+            None => write!(f, "Variant(<empty>)"),
+        }
+    }
+}
+
+impl Drop for Variant {
+    fn drop(&mut self) {
+        let Some(type_) = self.type_() else {
+            return;
+        };
+        let data = self.data_ptr();
+        unsafe {
+            type_.destruct(data);
+            if !self.is_inline() {
+                type_.allocator().free(data.as_ptr());
+            }
+        }
+        self.0.type_ = ptr::null();
+    }
+}